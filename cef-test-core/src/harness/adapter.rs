@@ -1,30 +1,103 @@
 use super::Feedback;
-use super::{TabSelector, ElementSelector};
+use super::{TabSelector, ElementSelector, KeyModifier, GamepadButton};
 
 /// API-specific implementation of interacting with CEF DevTools
 pub trait TestAdapter {
     /// Click on element in tab
     fn element_click(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
 
+    /// Double-click on element in tab
+    fn element_double_click(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
+
+    /// Right-click on element in tab
+    fn element_right_click(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
+
+    /// Click on element in tab at an offset from its top left corner
+    fn element_click_at(&mut self, tab: &TabSelector, element: &ElementSelector, x: f64, y: f64) -> Feedback;
+
+    /// Press and hold on element in tab for a period
+    fn element_long_press(&mut self, tab: &TabSelector, element: &ElementSelector, milliseconds: u64) -> Feedback;
+
+    /// Move mouse over element in tab
+    fn element_hover(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
+
+    /// Drag element in tab onto another element in the same tab
+    fn element_drag_to(&mut self, tab: &TabSelector, element: &ElementSelector, target: &ElementSelector) -> Feedback;
+
+    /// Drag element in tab by an offset
+    fn element_drag_by(&mut self, tab: &TabSelector, element: &ElementSelector, x: f64, y: f64) -> Feedback;
+
+    /// Swipe (touch drag) from element in tab by an offset
+    fn element_swipe(&mut self, tab: &TabSelector, element: &ElementSelector, x: f64, y: f64) -> Feedback;
+
+    /// Tap (touch) on element in tab
+    fn element_tap(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
+
     /// Wait for element to appear in tab
     fn element_wait(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
 
+    /// Wait for element in tab to be visible
+    fn element_wait_visible(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
+
+    /// Wait for element in tab to be hidden or removed
+    fn element_wait_hidden(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
+
+    /// Wait for element in tab to be enabled
+    fn element_wait_enabled(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
+
+    /// Wait for element text in tab to equal (exact) or contain text
+    fn element_wait_text(&mut self, tab: &TabSelector, element: &ElementSelector, text: &str, exact: bool) -> Feedback;
+
     /// Focus on element in tab
     fn element_focus(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
 
     /// Scroll to element in tab
     fn element_scroll_to(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
 
+    /// Type text into element in tab
+    fn element_type_text(&mut self, tab: &TabSelector, element: &ElementSelector, text: &str) -> Feedback;
+
+    /// Clear text from element in tab
+    fn element_clear(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
+
     /// Retrieve text in element in tab
     fn element_value(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
 
+    /// Count elements matching selector in tab
+    fn element_count(&mut self, tab: &TabSelector, element: &ElementSelector) -> Feedback;
+
     /// Retrieve text in element in tab
     fn element_attribute(&mut self, tab: &TabSelector, element: &ElementSelector, attribute: &str) -> Feedback;
 
+    /// Press key in tab, with modifier keys held down
+    fn key_press(&mut self, tab: &TabSelector, key: &str, modifiers: &[KeyModifier]) -> Feedback;
+
+    /// Press keys one after another in tab
+    fn key_sequence(&mut self, tab: &TabSelector, keys: &[String]) -> Feedback;
+
+    /// Press controller button in tab, for UIs driven by gamepad navigation
+    fn gamepad_button(&mut self, tab: &TabSelector, button: GamepadButton) -> Feedback;
+
     /// Pause execution in tab for a period
     fn wait(&mut self, tab: &TabSelector, milliseconds: u64) -> Feedback;
 
     /// Run Javascript in tab
     fn evaluate(&mut self, tab: &TabSelector, script: &str) -> Feedback;
+
+    /// Wait for Javascript in tab to evaluate to a truthy value
+    fn wait_script(&mut self, tab: &TabSelector, script: &str) -> Feedback;
+
+    /// Wait for tab to make no network requests for a period.
+    /// Requests already in flight when the wait starts aren't counted, so they may still be pending once it's idle.
+    fn wait_network_idle(&mut self, tab: &TabSelector, idle_milliseconds: u64) -> Feedback;
+
+    /// Wait for page in tab to finish loading
+    fn wait_load(&mut self, tab: &TabSelector) -> Feedback;
+
+    /// Wait for tab to exist
+    fn wait_tab(&mut self, tab: &TabSelector) -> Feedback;
+
+    /// Set time limit for following operations (None for the adapter's default)
+    fn set_timeout(&mut self, timeout: Option<std::time::Duration>);
     // TODO
 }
@@ -1,5 +1,5 @@
 /// Harness information for a test runner
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Feedback {
     /// Start of run (no feedback to provide)
     Start,
@@ -8,11 +8,13 @@ pub enum Feedback {
     /// Last instruction returned a value
     Value(serde_json::Value),
     /// Last instruction was an assertion and it failed
-    AssertFailure,
+    AssertFailure(TestError),
     /// Last instruction raised an error
-    Error,
+    Error(TestError),
     /// Last instruction was not supported by adaptor
-    Unsupported,
+    Unsupported(TestError),
+    /// Last instruction did not complete in time
+    Timeout(TestError),
 }
 
 impl Feedback {
@@ -29,7 +31,8 @@ impl Feedback {
     /// Feedback is indicative of an error
     pub fn is_err(&self) -> bool {
         match self {
-            Self::Error => true,
+            Self::Error(_) => true,
+            Self::Timeout(_) => true,
             _ => false,
         }
     }
@@ -37,10 +40,89 @@ impl Feedback {
     /// Feedback is indicative of a failing test
     pub fn is_fail(&self) -> bool {
         match self {
-            Self::AssertFailure => true,
-            Self::Error => true,
-            Self::Unsupported => true,
+            Self::AssertFailure(_) => true,
+            Self::Error(_) => true,
+            Self::Unsupported(_) => true,
+            Self::Timeout(_) => true,
             _ => false,
         }
     }
+
+    /// Cause of the failure, if the feedback is failing
+    pub fn error(&self) -> Option<&TestError> {
+        match self {
+            Self::AssertFailure(e) => Some(e),
+            Self::Error(e) => Some(e),
+            Self::Unsupported(e) => Some(e),
+            Self::Timeout(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Detailed cause of a failing instruction
+#[derive(Clone, Debug)]
+pub enum TestError {
+    /// No tab matched the tab selector
+    TabNotFound {
+        /// Tab selector
+        tab: String,
+    },
+    /// No element matched the element selector
+    ElementNotFound {
+        /// Tab selector
+        tab: String,
+        /// Element selector
+        element: String,
+    },
+    /// Javascript threw an exception
+    Javascript {
+        /// Exception message
+        message: String,
+        /// Exception stack trace
+        stack: Option<String>,
+    },
+    /// Operation did not complete in time
+    Timeout {
+        /// Description of the operation which timed out
+        operation: String,
+        /// Time allowed for the operation, if known
+        milliseconds: Option<u64>,
+    },
+    /// DevTools connection failed
+    Transport(String),
+    /// Assertion comparison was not satisfied
+    AssertMismatch {
+        /// Expected value description
+        expected: String,
+        /// Actual value
+        actual: Option<serde_json::Value>,
+        /// Pseudocode of the failing (part of the) assertion
+        failure: String,
+    },
+    /// Any other error
+    Other(String),
 }
+
+impl std::fmt::Display for TestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TabNotFound { tab } => write!(f, "Failed to find tab {}", tab),
+            Self::ElementNotFound { tab, element } => write!(f, "Failed to find element {} in {}", element, tab),
+            Self::Javascript { message, stack } => {
+                write!(f, "Javascript exception: {}", message)?;
+                if let Some(stack) = stack {
+                    write!(f, "\n{}", stack)?;
+                }
+                Ok(())
+            },
+            Self::Timeout { operation, milliseconds: Some(ms) } => write!(f, "Timed out after {}ms: {}", ms, operation),
+            Self::Timeout { operation, milliseconds: None } => write!(f, "Timed out: {}", operation),
+            Self::Transport(msg) => write!(f, "DevTools transport failure: {}", msg),
+            Self::AssertMismatch { failure, .. } => write!(f, "Assertion failed: {}", failure),
+            Self::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TestError {}
@@ -1,10 +1,16 @@
 use super::{TestRunner, TestAdapter, TestMetadata};
-use super::{Instruction, TestAssert, TestOp, Feedback, GeneralOpType, ElementOpType, TabOpType, GeneralAssertType, ElementAssertionType, TabAssert, Comparison};
+use super::{Instruction, TestAssert, TestOp, Feedback, TestError, GeneralOpType, ElementOpType, TabOpType, GeneralAssertType, ElementAssertionType, TabAssert, Comparison};
+
+/// Time allowed for eventual assertions when the instruction has no time limit
+const DEFAULT_POLL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
 
 /// Harness which runs one or more tests
 pub struct TestHarness<R: TestRunner, A: TestAdapter> {
     tests: Vec<R>,
     adapter: A,
+    timeout: Option<std::time::Duration>,
+    before_all: Option<R>,
+    after_all: Option<R>,
 }
 
 impl<R: TestRunner, A: TestAdapter> TestHarness<R, A> {
@@ -13,45 +19,104 @@ impl<R: TestRunner, A: TestAdapter> TestHarness<R, A> {
         Self {
             adapter,
             tests,
+            timeout: None,
+            before_all: None,
+            after_all: None,
+        }
+    }
+
+    /// Use a default time limit for instructions which don't specify one
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run a hook once before all tests; the tests are skipped if it fails
+    pub fn with_before_all(mut self, hook: R) -> Self {
+        self.before_all = Some(hook);
+        self
+    }
+
+    /// Run a hook once after all tests, even if they failed
+    pub fn with_after_all(mut self, hook: R) -> Self {
+        self.after_all = Some(hook);
+        self
+    }
+
+    fn translate_assertion(&mut self, assertion: TestAssert, timeout: Option<std::time::Duration>) -> Feedback {
+        let comparison = assertion.assertion.comparison();
+        if let Some(polling) = &assertion.eventually {
+            let deadline = polling.timeout.or(timeout).unwrap_or(DEFAULT_POLL_TIMEOUT);
+            let start = std::time::Instant::now();
+            let mut attempts = 0;
+            loop {
+                // don't let the adapter wait beyond the deadline
+                self.adapter.set_timeout(Some(deadline.saturating_sub(start.elapsed())));
+                let feedback = self.query_assertion(&assertion);
+                attempts += 1;
+                let value = if let Feedback::Value(v) = &feedback { Some(v) } else { None };
+                if comparison.compare(value) {
+                    return Self::maybe_assert(feedback, comparison);
+                }
+                let elapsed = start.elapsed();
+                if elapsed >= deadline {
+                    return match Self::maybe_assert(feedback, comparison) {
+                        Feedback::AssertFailure(TestError::AssertMismatch { expected, actual, failure }) => Feedback::AssertFailure(TestError::AssertMismatch {
+                            expected,
+                            actual,
+                            failure: format!("{} (last observed after {} attempts in {}ms)", failure, attempts, elapsed.as_millis()),
+                        }),
+                        feedback => feedback,
+                    };
+                }
+                log::debug!("Assertion not satisfied yet: {}", comparison.pseudocode_assert(value));
+                std::thread::sleep(polling.interval.min(deadline - elapsed));
+            }
+        } else {
+            let feedback = self.query_assertion(&assertion);
+            Self::maybe_assert(feedback, comparison)
         }
     }
 
-    fn translate_assertion(&mut self, assertion: TestAssert) -> Feedback {
-        match assertion.assertion {
+    fn query_assertion(&mut self, assertion: &TestAssert) -> Feedback {
+        match &assertion.assertion {
             GeneralAssertType::Element(elem) => {
-                match elem.assert {
-                    ElementAssertionType::Value(comparison) =>
-                        Self::maybe_assert(
-                            self.adapter.element_value(&assertion.context, &elem.element),
-                            comparison
-                        ),
-                    ElementAssertionType::Attribute { attribute, comparison } =>
-                        Self::maybe_assert(
-                            self.adapter.element_attribute(&assertion.context, &elem.element, &attribute),
-                            comparison
-                        )
+                match &elem.assert {
+                    ElementAssertionType::Value(_) => self.adapter.element_value(&assertion.context, &elem.element),
+                    ElementAssertionType::Attribute { attribute, .. } => self.adapter.element_attribute(&assertion.context, &elem.element, attribute),
+                    ElementAssertionType::Count(_) => self.adapter.element_count(&assertion.context, &elem.element),
                 }
             },
-            GeneralAssertType::Tab(TabAssert::Evaluate { script, comparison }) => Self::maybe_assert(self.adapter.evaluate(&assertion.context, &script), comparison)
+            GeneralAssertType::Tab(TabAssert::Evaluate { script, .. }) => self.adapter.evaluate(&assertion.context, script),
         }
     }
 
-    fn maybe_assert(adaptor_feedback: Feedback, cmp: Comparison) -> Feedback {
+    fn maybe_assert(adaptor_feedback: Feedback, cmp: &Comparison) -> Feedback {
         if let Feedback::Value(v) = &adaptor_feedback {
             if cmp.compare(Some(v)) {
                 log::info!("Assertion satisfied: {}", cmp.pseudocode_assert(Some(v)));
                 adaptor_feedback
             } else {
                 log::error!("Assertion failed: {}", cmp.pseudocode_assert(Some(v)));
-                Feedback::AssertFailure
+                Feedback::AssertFailure(TestError::AssertMismatch {
+                    expected: cmp.to_string(),
+                    actual: Some(v.clone()),
+                    failure: cmp.pseudocode_failure(Some(v)).unwrap_or_default(),
+                })
             }
         } else {
             if cmp.compare(None) {
                 log::info!("Assertion satisfied: {}", cmp.pseudocode_assert(None));
-                adaptor_feedback
+                // the missing value is what was expected, so the adaptor's error doesn't matter
+                Feedback::Success
             } else {
                 log::error!("Assertion failed: {}", cmp.pseudocode_assert(None));
-                Feedback::AssertFailure
+                // the adaptor's error explains the missing value better than a mismatch would
+                Feedback::AssertFailure(adaptor_feedback.error().cloned().unwrap_or_else(|| TestError::AssertMismatch {
+                    expected: cmp.to_string(),
+                    actual: None,
+                    failure: cmp.pseudocode_failure(None).unwrap_or_default(),
+                }))
             }
         }
     }
@@ -61,42 +126,129 @@ impl<R: TestRunner, A: TestAdapter> TestHarness<R, A> {
             GeneralOpType::Element(elem) => {
                 match elem.op {
                     ElementOpType::Click => self.adapter.element_click(&op.context, &elem.context),
+                    ElementOpType::DoubleClick => self.adapter.element_double_click(&op.context, &elem.context),
+                    ElementOpType::RightClick => self.adapter.element_right_click(&op.context, &elem.context),
+                    ElementOpType::ClickAt { x, y } => self.adapter.element_click_at(&op.context, &elem.context, x, y),
+                    ElementOpType::LongPress(ms) => self.adapter.element_long_press(&op.context, &elem.context, ms),
+                    ElementOpType::Hover => self.adapter.element_hover(&op.context, &elem.context),
+                    ElementOpType::DragTo(target) => self.adapter.element_drag_to(&op.context, &elem.context, &target),
+                    ElementOpType::DragBy { x, y } => self.adapter.element_drag_by(&op.context, &elem.context, x, y),
+                    ElementOpType::Swipe { x, y } => self.adapter.element_swipe(&op.context, &elem.context, x, y),
+                    ElementOpType::Tap => self.adapter.element_tap(&op.context, &elem.context),
                     ElementOpType::WaitFor => self.adapter.element_wait(&op.context, &elem.context),
+                    ElementOpType::WaitForVisible => self.adapter.element_wait_visible(&op.context, &elem.context),
+                    ElementOpType::WaitForHidden => self.adapter.element_wait_hidden(&op.context, &elem.context),
+                    ElementOpType::WaitForEnabled => self.adapter.element_wait_enabled(&op.context, &elem.context),
+                    ElementOpType::WaitForText(text) => self.adapter.element_wait_text(&op.context, &elem.context, &text, true),
+                    ElementOpType::WaitForTextContains(text) => self.adapter.element_wait_text(&op.context, &elem.context, &text, false),
                     ElementOpType::Focus => self.adapter.element_focus(&op.context, &elem.context),
                     ElementOpType::ScrollTo => self.adapter.element_scroll_to(&op.context, &elem.context),
+                    ElementOpType::TypeText(text) => self.adapter.element_type_text(&op.context, &elem.context, &text),
+                    ElementOpType::Clear => self.adapter.element_clear(&op.context, &elem.context),
+                    ElementOpType::Count => self.adapter.element_count(&op.context, &elem.context),
+                    ElementOpType::GetText => self.adapter.element_value(&op.context, &elem.context),
+                    ElementOpType::GetAttribute(attribute) => self.adapter.element_attribute(&op.context, &elem.context, &attribute),
                 }
             },
             GeneralOpType::Tab(TabOpType::Sleep(ms)) => self.adapter.wait(&op.context, ms),
             GeneralOpType::Tab(TabOpType::Evaluate(js)) => self.adapter.evaluate(&op.context, &js),
+            GeneralOpType::Tab(TabOpType::KeyPress { key, modifiers }) => self.adapter.key_press(&op.context, &key, &modifiers),
+            GeneralOpType::Tab(TabOpType::KeySequence(keys)) => self.adapter.key_sequence(&op.context, &keys),
+            GeneralOpType::Tab(TabOpType::GamepadButton(button)) => self.adapter.gamepad_button(&op.context, button),
+            GeneralOpType::Tab(TabOpType::WaitForScript(js)) => self.adapter.wait_script(&op.context, &js),
+            GeneralOpType::Tab(TabOpType::WaitForNetworkIdle(ms)) => self.adapter.wait_network_idle(&op.context, ms),
+            GeneralOpType::Tab(TabOpType::WaitForLoad) => self.adapter.wait_load(&op.context),
+            GeneralOpType::Tab(TabOpType::WaitForTab) => self.adapter.wait_tab(&op.context),
         }
     }
 
     fn translate_instruction(&mut self, instruction: Instruction) -> Feedback {
-        match instruction {
-            Instruction::Assertion(a) => self.translate_assertion(a),
+        let timeout = match &instruction {
+            Instruction::Assertion(a) => a.timeout,
+            Instruction::Operation(i) => i.timeout,
+        }.or(self.timeout);
+        self.adapter.set_timeout(timeout);
+        let feedback = match instruction {
+            Instruction::Assertion(a) => self.translate_assertion(a, timeout),
             Instruction::Operation(i) => self.translate_ui_op(i),
+        };
+        Self::distinguish_timeout(feedback, timeout)
+    }
+
+    /// Report timeouts separately from other errors
+    fn distinguish_timeout(feedback: Feedback, timeout: Option<std::time::Duration>) -> Feedback {
+        match feedback {
+            Feedback::Error(TestError::Timeout { operation, milliseconds })
+            | Feedback::AssertFailure(TestError::Timeout { operation, milliseconds }) => Feedback::Timeout(TestError::Timeout {
+                operation,
+                milliseconds: milliseconds.or(timeout.map(|t| t.as_millis() as u64)),
+            }),
+            feedback => feedback,
         }
     }
 
-    /// Perform the tests
-    pub fn execute(mut self) -> Result<A, Vec<TestMetadata>> {
-        let tests: Vec<R> = self.tests.drain(..).collect();
-        let mut failures = Vec::with_capacity(tests.len());
-        for mut test in tests {
+    /// Run one test to completion, retrying it if the runner wants to
+    fn run_test(&mut self, test: &mut R) -> TestMetadata {
+        let metadata = test.meta();
+        log::info!("Starting test {}: {}", metadata.id, metadata.name);
+        let metadata = loop {
             let mut feedback = Feedback::Start;
             let mut is_success = true;
-            let metadata = test.meta();
-            log::info!("Starting test {}: {}", metadata.id, metadata.name);
+            let mut error = None;
             while let Some(instruction) = test.next(feedback) {
                 feedback = self.translate_instruction(instruction);
                 is_success &= feedback.is_ok();
+                if error.is_none() {
+                    error = feedback.error().cloned();
+                }
             }
             let mut metadata = test.meta();
-            metadata.success &= is_success;
-            if metadata.success {
-                log::info!("{}", metadata);
-            } else {
-                log::error!("{}", metadata);
+            if !test.recovers_failures() {
+                metadata.success &= is_success;
+            }
+            if !metadata.success && metadata.error.is_none() {
+                metadata.error = error;
+            }
+            if !metadata.success && test.retry() {
+                log::warn!("{}, retrying test", metadata);
+                continue;
+            }
+            break metadata;
+        };
+        if metadata.success && metadata.retries != 0 {
+            log::warn!("{} (flaky)", metadata);
+        } else if metadata.success {
+            log::info!("{}", metadata);
+        } else {
+            log::error!("{}", metadata);
+        }
+        metadata
+    }
+
+    /// Perform the tests
+    pub fn execute(mut self) -> Result<A, Vec<TestMetadata>> {
+        let tests: Vec<R> = self.tests.drain(..).collect();
+        let mut failures = Vec::with_capacity(tests.len());
+        let mut is_ready = true;
+        if let Some(mut hook) = self.before_all.take() {
+            let metadata = self.run_test(&mut hook);
+            if !metadata.success {
+                log::error!("Before-all hook failed, skipping {} tests", tests.len());
+                is_ready = false;
+                failures.push(metadata);
+            }
+        }
+        if is_ready {
+            for mut test in tests {
+                let metadata = self.run_test(&mut test);
+                if !metadata.success {
+                    failures.push(metadata);
+                }
+            }
+        }
+        if let Some(mut hook) = self.after_all.take() {
+            let metadata = self.run_test(&mut hook);
+            if !metadata.success {
                 failures.push(metadata);
             }
         }
@@ -105,6 +257,27 @@ impl<R: TestRunner, A: TestAdapter> TestHarness<R, A> {
         } else {
             Err(failures)
         }
+    }
+}
 
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{JsonRunner, HeadlessAdapter};
+
+    type Harness = TestHarness<JsonRunner, HeadlessAdapter>;
+
+    fn missing() -> Feedback {
+        Feedback::Error(TestError::ElementNotFound { tab: "t".into(), element: "e".into() })
+    }
+
+    #[test]
+    fn assert_missing_value() {
+        let not_exists = Comparison::Not(Box::new(Comparison::Exists));
+        assert!(matches!(Harness::maybe_assert(missing(), &not_exists), Feedback::Success));
+        match Harness::maybe_assert(missing(), &Comparison::Exists) {
+            Feedback::AssertFailure(TestError::ElementNotFound { element, .. }) => assert_eq!(element, "e"),
+            feedback => panic!("Expected element not found assertion failure, got {:?}", feedback),
+        }
     }
 }
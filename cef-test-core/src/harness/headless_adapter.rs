@@ -1,18 +1,37 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 use regex::Regex;
 use headless_chrome::{Browser, Tab, Element};
+use headless_chrome::browser::tab::ModifierKey;
+use headless_chrome::browser::tab::point::Point;
+use headless_chrome::browser::transport::ConnectionClosed;
+use headless_chrome::protocol::cdp::{Emulation, Input, Network, Runtime, DOM};
+use headless_chrome::protocol::cdp::types::Event;
 
 use crate::cef::WebContent;
-use super::{TestAdapter, TabSelector, ElementSelector, Feedback};
+use super::selector_js;
+use super::{TestAdapter, TabSelector, ElementSelector, Feedback, TestError, KeyModifier, GamepadButton};
+
+/// Same as headless_chrome's default element timeout
+const DEFAULT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+/// Rejection message of scripts which ran out of time
+const SCRIPT_TIMEOUT_MESSAGE: &str = "cef-test script timeout";
+/// DevTools error for scripts interrupted by navigation
+const CONTEXT_DESTROYED_MESSAGE: &str = "Execution context was destroyed";
+const STABLE_CHECK_LIMIT: usize = 20;
+const STABLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+const DRAG_STEPS: u32 = 10;
+const DRAG_STEP_INTERVAL: std::time::Duration = std::time::Duration::from_millis(16);
 
 /// Headless Chrome Adapter for CEF
 pub struct HeadlessAdapter {
     web_content: Vec<WebContent>,
     connections: HashMap<String, Browser>,
     domain_name: String,
-    port_num: u16
+    port_num: u16,
+    timeout: std::time::Duration,
 }
 
 impl HeadlessAdapter {
@@ -37,6 +56,7 @@ impl HeadlessAdapter {
             connections: conn_map,
             domain_name: domain_name.to_owned(),
             port_num: port,
+            timeout: DEFAULT_TIMEOUT,
         })
     }
 
@@ -67,6 +87,7 @@ impl HeadlessAdapter {
                     pattern.is_match(web_content.url())
                 },
                 TabSelector::Id(id) => id == web_content.id(),
+                TabSelector::Frame { .. } => false,
             };
             if is_match {
                 let new_browser = Browser::connect(web_content.debug_url().to_owned()).map_err(|e| format!("{}", e))?;
@@ -97,6 +118,7 @@ impl HeadlessAdapter {
                             let pattern = Regex::new(pattern).map_err(|x| x.to_string())?;
                             pattern.is_match(&info.url)
                         },
+                        TabSelector::Frame { .. } => false,
                     };
                     if is_match {
                         return Ok(Some(tab.clone()));
@@ -107,207 +129,784 @@ impl HeadlessAdapter {
         Ok(None)
     }
 
-    fn select_tab(&mut self, tab: &TabSelector, can_refresh: bool) -> Option<Arc<Tab>> {
-        let mut tab_result = None;
-        match self.tab_connection(tab) {
-            Ok(tab) => tab_result = tab,
-            Err(e) => log::warn!("Failed to retrieve tab {} by connections: {}", tab, e),
-        }
-        if tab_result.is_some() {
-            return tab_result;
+    fn select_tab(&mut self, tab: &TabSelector, can_refresh: bool) -> Result<Arc<Tab>, TestError> {
+        if let TabSelector::Frame { tab, .. } = tab {
+            // frames are searched inside their tab's page
+            return self.select_tab(tab, can_refresh);
         }
-        match self.tab_web_content(tab) {
-            Ok(tab) => tab_result = tab,
-            Err(e) => log::warn!("Failed to retrieve tab {} by web content: {}", tab, e),
-        }
-        if tab_result.is_some() || !can_refresh {
-            tab_result
-        } else {
+        let start = std::time::Instant::now();
+        loop {
+            match self.tab_connection(tab) {
+                Ok(Some(tab)) => return Ok(tab),
+                Ok(None) => {},
+                Err(e) => log::warn!("Failed to retrieve tab {} by connections: {}", tab, e),
+            }
+            match self.tab_web_content(tab) {
+                Ok(Some(tab)) => return Ok(tab),
+                Ok(None) => {},
+                Err(e) => log::warn!("Failed to retrieve tab {} by web content: {}", tab, e),
+            }
+            if !can_refresh || start.elapsed() > self.timeout {
+                return Err(TestError::TabNotFound { tab: tab.to_string() });
+            }
             log::info!("Tab not found, refreshing WebContent");
             match WebContent::load_all(&self.domain_name, self.port_num) {
-                Ok(content) => {
-                    self.web_content = content;
-                    self.select_tab(tab, false)
-                },
+                Ok(content) => self.web_content = content,
                 Err(e) => {
                     log::warn!("Failed to refresh WebContent: {}", e);
-                    None
+                    return Err(TestError::Transport(e.to_string()));
                 }
             }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn select_element<'a>(&mut self, tab: &'a Tab, tab_s: &TabSelector, element: &ElementSelector) -> Result<Element<'a>, TestError> {
+        let result = match (tab_s, element) {
+            (TabSelector::Frame { .. }, _) => Self::wait_for_js_element(tab, &selector_js::find_first(tab_s, element), self.timeout),
+            (_, ElementSelector::CSS(css)) => tab.wait_for_element_with_custom_timeout(css, self.timeout),
+            (_, ElementSelector::XPath(xpath)) => tab.wait_for_xpath_with_custom_timeout(xpath, self.timeout),
+            _ => Self::wait_for_js_element(tab, &selector_js::find_first(tab_s, element), self.timeout),
+        };
+        result.map_err(|e| if e.is::<headless_chrome::util::Timeout>() {
+            TestError::ElementNotFound { tab: tab_s.to_string(), element: element.to_string() }
+        } else {
+            Self::adapter_error(&format!("retrieve element {}", element), e)
+        })
+    }
+
+    /// Poll a Javascript expression until it evaluates to an element
+    fn wait_for_js_element<'a>(tab: &'a Tab, finder: &str, timeout: std::time::Duration) -> anyhow::Result<Element<'a>> {
+        let start = std::time::Instant::now();
+        loop {
+            let found = Self::evaluate_remote(tab, finder, false, timeout).map_err(anyhow::Error::new)?;
+            if let Some(object_id) = found.object_id {
+                // make sure the document is known to DevTools before requesting nodes in it
+                tab.get_document()?;
+                let node_id = tab.call_method(DOM::RequestNode { object_id })?.node_id;
+                return Element::new(tab, node_id);
+            }
+            if start.elapsed() > timeout {
+                return Err(headless_chrome::util::Timeout.into());
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn with_element<F: FnOnce(&Element) -> Feedback>(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, op: F) -> Feedback {
+        match self.select_tab(tab_s, true) {
+            Ok(tab) => match self.select_element(&tab, tab_s, element_s) {
+                Ok(element) => op(&element),
+                Err(e) => Self::fail(e),
+            },
+            Err(e) => Self::fail(e),
+        }
+    }
+
+    /// Poll Javascript until it evaluates to a truthy value.
+    /// Exceptions (e.g. from objects which don't exist yet, or navigation) are retried until the time runs out.
+    fn wait_for_script(tab: &Tab, script: &str, timeout: std::time::Duration, operation: &str) -> Result<(), TestError> {
+        let start = std::time::Instant::now();
+        loop {
+            let remaining = timeout.saturating_sub(start.elapsed()).max(POLL_INTERVAL);
+            let last_exception = match Self::evaluate_script(tab, script, remaining) {
+                Ok(value) if crate::util::is_truthy(&value) => return Ok(()),
+                Ok(_) => None,
+                Err(TestError::Javascript { message, .. }) => Some(message),
+                Err(e) => return Err(e),
+            };
+            if start.elapsed() > timeout {
+                let operation = match last_exception {
+                    Some(message) => format!("{} (last exception: {})", operation, message),
+                    None => operation.to_owned(),
+                };
+                return Err(TestError::Timeout { operation, milliseconds: Some(timeout.as_millis() as u64) });
+            }
+            std::thread::sleep(POLL_INTERVAL);
         }
     }
 
-    fn select_element<'a>(&mut self, tab: &'a Tab, element: &ElementSelector) -> Option<Element<'a>> {
-        match element {
-            ElementSelector::CSS(css) => {
-                tab.wait_for_element(css)
-                    .map_err(|e| log::error!("Failed to retrieve element {}: {}", element, e))
-                    .ok()
+    fn wait_element_condition(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, check: &str, condition: &str) -> Feedback {
+        match self.select_tab(tab_s, true) {
+            Ok(tab) => {
+                let operation = format!("wait for {} in {} to {}", element_s, tab_s, condition);
+                match Self::wait_for_script(&tab, &selector_js::check_first(tab_s, element_s, check), self.timeout, &operation) {
+                    Ok(_) => Feedback::Success,
+                    Err(e) => Self::fail(e),
+                }
             },
+            Err(e) => Self::fail(e),
+        }
+    }
+
+    fn evaluate_script(tab: &Tab, script: &str, timeout: std::time::Duration) -> Result<serde_json::Value, TestError> {
+        Self::evaluate_remote(tab, script, true, timeout).map(|result| result.value.unwrap_or(serde_json::Value::Null))
+    }
+
+    fn evaluate_remote(tab: &Tab, script: &str, by_value: bool, timeout: std::time::Duration) -> Result<Runtime::RemoteObject, TestError> {
+        let milliseconds = timeout.as_millis() as u64;
+        // DevTools' timeout only interrupts synchronous execution, so race promises against a timer too
+        let expression = format!(
+            "Promise.race([(0, eval)({}), new Promise((_, reject) => setTimeout(() => reject(new Error({})), {}))])",
+            serde_json::Value::from(script), serde_json::Value::from(SCRIPT_TIMEOUT_MESSAGE), milliseconds);
+        let result = tab.call_method(Runtime::Evaluate {
+            expression,
+            object_group: None,
+            include_command_line_api: Some(false),
+            silent: Some(false),
+            context_id: None,
+            return_by_value: Some(by_value),
+            generate_preview: Some(false),
+            user_gesture: Some(false),
+            await_promise: Some(true),
+            throw_on_side_effect: None,
+            timeout: Some(milliseconds as f64),
+            disable_breaks: None,
+            repl_mode: None,
+            allow_unsafe_eval_blocked_by_csp: None,
+            unique_context_id: None,
+            serialization_options: None,
+        }).map_err(|e| if e.to_string().contains(CONTEXT_DESTROYED_MESSAGE) {
+            // the page navigated while the script was running
+            TestError::Javascript { message: e.to_string(), stack: None }
+        } else {
+            Self::adapter_error("evaluate script", e)
+        })?;
+        if let Some(details) = result.exception_details {
+            let description = details.exception.and_then(|obj| obj.description);
+            let message = description.as_deref()
+                .and_then(|desc| desc.lines().next())
+                .map(|line| line.to_owned())
+                .unwrap_or(details.text);
+            if message.contains(SCRIPT_TIMEOUT_MESSAGE) {
+                return Err(TestError::Timeout { operation: "evaluate script".to_owned(), milliseconds: Some(milliseconds) });
+            }
+            let stack = details.stack_trace.map(|trace| trace.call_frames.iter()
+                .map(|frame| format!("    at {} ({}:{}:{})",
+                    if frame.function_name.is_empty() { "<anonymous>" } else { &frame.function_name },
+                    frame.url, frame.line_number + 1, frame.column_number + 1))
+                .collect::<Vec<_>>()
+                .join("\n"));
+            Err(TestError::Javascript { message, stack })
+        } else {
+            Ok(result.result)
+        }
+    }
+
+    fn adapter_error(operation: &str, e: anyhow::Error) -> TestError {
+        if e.is::<ConnectionClosed>() {
+            TestError::Transport(e.to_string())
+        } else if e.is::<headless_chrome::util::Timeout>() {
+            TestError::Timeout { operation: operation.to_owned(), milliseconds: None }
+        } else {
+            TestError::Other(format!("Failed to {}: {}", operation, e))
+        }
+    }
+
+    /// Scroll element into view and find the point to interact with, once the element stops moving.
+    /// Without an offset this is the center of the element, otherwise it's relative to the top left corner.
+    fn element_point(element: &Element, offset: Option<(f64, f64)>) -> anyhow::Result<Point> {
+        element.scroll_into_view()?;
+        let mut quad = element.get_box_model()?.border;
+        for _ in 0..STABLE_CHECK_LIMIT {
+            std::thread::sleep(STABLE_CHECK_INTERVAL);
+            let next = element.get_box_model()?.border;
+            let is_stable = next.top_left.x == quad.top_left.x
+                && next.top_left.y == quad.top_left.y
+                && next.bottom_right.x == quad.bottom_right.x
+                && next.bottom_right.y == quad.bottom_right.y;
+            quad = next;
+            if is_stable {
+                break;
+            }
+        }
+        Ok(match offset {
+            Some((x, y)) => Point {
+                x: quad.top_left.x + x,
+                y: quad.top_left.y + y,
+            },
+            None => Point {
+                x: (quad.top_left.x + quad.top_right.x + quad.bottom_left.x + quad.bottom_right.x) / 4.0,
+                y: (quad.top_left.y + quad.top_right.y + quad.bottom_left.y + quad.bottom_right.y) / 4.0,
+            },
+        })
+    }
+
+    fn mouse_event(tab: &Tab, event: Input::DispatchMouseEventTypeOption, point: Point, button: Input::MouseButton, buttons: u32, click_count: u32) -> anyhow::Result<()> {
+        tab.call_method(Input::DispatchMouseEvent {
+            Type: event,
+            x: point.x,
+            y: point.y,
+            modifiers: None,
+            timestamp: None,
+            button: Some(button),
+            buttons: Some(buttons),
+            click_count: Some(click_count),
+            force: None,
+            tangential_pressure: None,
+            tilt_x: None,
+            tilt_y: None,
+            twist: None,
+            delta_x: None,
+            delta_y: None,
+            pointer_Type: None,
+        })?;
+        Ok(())
+    }
+
+    /// Move the mouse to the element and click it, holding each press for a period
+    fn pointer_click(element: &Element, button: Input::MouseButton, click_count: u32, offset: Option<(f64, f64)>, hold_ms: u64) -> anyhow::Result<()> {
+        let point = Self::element_point(element, offset)?;
+        let tab = element.parent;
+        let pressed = match button {
+            Input::MouseButton::Left => 1,
+            Input::MouseButton::Right => 2,
+            Input::MouseButton::Middle => 4,
+            _ => 0,
+        };
+        Self::mouse_event(tab, Input::DispatchMouseEventTypeOption::MouseMoved, point, Input::MouseButton::None, 0, 0)?;
+        // consecutive presses with increasing click count are what make a double-click
+        for count in 1..=click_count {
+            Self::mouse_event(tab, Input::DispatchMouseEventTypeOption::MousePressed, point, button.clone(), pressed, count)?;
+            if hold_ms != 0 {
+                std::thread::sleep(std::time::Duration::from_millis(hold_ms));
+            }
+            Self::mouse_event(tab, Input::DispatchMouseEventTypeOption::MouseReleased, point, button.clone(), 0, count)?;
+        }
+        Ok(())
+    }
+
+    /// Press at start, move through intermediate points to end, then release
+    fn pointer_drag(tab: &Tab, start: Point, end: Point) -> anyhow::Result<()> {
+        Self::mouse_event(tab, Input::DispatchMouseEventTypeOption::MouseMoved, start, Input::MouseButton::None, 0, 0)?;
+        Self::mouse_event(tab, Input::DispatchMouseEventTypeOption::MousePressed, start, Input::MouseButton::Left, 1, 1)?;
+        for step in 1..=DRAG_STEPS {
+            std::thread::sleep(DRAG_STEP_INTERVAL);
+            Self::mouse_event(tab, Input::DispatchMouseEventTypeOption::MouseMoved, Self::interpolate(start, end, step), Input::MouseButton::Left, 1, 0)?;
+        }
+        Self::mouse_event(tab, Input::DispatchMouseEventTypeOption::MouseReleased, end, Input::MouseButton::Left, 0, 1)
+    }
+
+    fn touch_event(tab: &Tab, event: Input::DispatchTouchEventTypeOption, point: Option<Point>) -> anyhow::Result<()> {
+        tab.call_method(Input::DispatchTouchEvent {
+            Type: event,
+            touch_points: point.map(|point| vec![Input::TouchPoint {
+                x: point.x,
+                y: point.y,
+                radius_x: None,
+                radius_y: None,
+                rotation_angle: None,
+                force: None,
+                tangential_pressure: None,
+                tilt_x: None,
+                tilt_y: None,
+                twist: None,
+                id: Some(0.0),
+            }]).unwrap_or_default(),
+            modifiers: None,
+            timestamp: None,
+        })?;
+        Ok(())
+    }
+
+    /// Touch at start, move through intermediate points to end, then lift.
+    /// Touch emulation is only enabled during the gesture, since it changes how pages lay themselves out.
+    fn touch_gesture(tab: &Tab, start: Point, end: Point) -> anyhow::Result<()> {
+        tab.call_method(Emulation::SetTouchEmulationEnabled {
+            enabled: true,
+            max_touch_points: Some(1),
+        })?;
+        let result = Self::touch_points(tab, start, end);
+        let disabled = tab.call_method(Emulation::SetTouchEmulationEnabled {
+            enabled: false,
+            max_touch_points: None,
+        });
+        result?;
+        disabled?;
+        Ok(())
+    }
+
+    fn touch_points(tab: &Tab, start: Point, end: Point) -> anyhow::Result<()> {
+        Self::touch_event(tab, Input::DispatchTouchEventTypeOption::TouchStart, Some(start))?;
+        if start != end {
+            for step in 1..=DRAG_STEPS {
+                std::thread::sleep(DRAG_STEP_INTERVAL);
+                Self::touch_event(tab, Input::DispatchTouchEventTypeOption::TouchMove, Some(Self::interpolate(start, end, step)))?;
+            }
+        }
+        Self::touch_event(tab, Input::DispatchTouchEventTypeOption::TouchEnd, None)
+    }
+
+    fn interpolate(start: Point, end: Point, step: u32) -> Point {
+        let progress = step as f64 / DRAG_STEPS as f64;
+        Point {
+            x: start.x + (end.x - start.x) * progress,
+            y: start.y + (end.y - start.y) * progress,
+        }
+    }
+
+    fn gesture_feedback(element_s: &ElementSelector, gesture: &str, result: anyhow::Result<()>) -> Feedback {
+        match result {
+            Ok(_) => Feedback::Success,
+            Err(e) => {
+                log::error!("Failed to {} element {}: {}", gesture, element_s, e);
+                Feedback::Error(Self::adapter_error(&format!("{} element", gesture), e))
+            }
+        }
+    }
+
+    fn click_feedback(element_s: &ElementSelector, result: anyhow::Result<()>) -> Feedback {
+        match result {
+            Ok(_) => Feedback::Success,
+            Err(e) => {
+                log::error!("Failed to click on element {}: {}", element_s, e);
+                Feedback::Error(Self::adapter_error("click on element", e))
+            }
+        }
+    }
+
+    /// Button code used by the Steam client's gamepad navigation events
+    fn steam_button_code(button: GamepadButton) -> u32 {
+        match button {
+            GamepadButton::A => 1,
+            GamepadButton::B => 2,
+            GamepadButton::X => 3,
+            GamepadButton::Y => 4,
+            GamepadButton::LeftBumper => 5,
+            GamepadButton::RightBumper => 6,
+            GamepadButton::LeftTrigger => 7,
+            GamepadButton::RightTrigger => 8,
+            GamepadButton::DPadUp => 9,
+            GamepadButton::DPadDown => 10,
+            GamepadButton::DPadLeft => 11,
+            GamepadButton::DPadRight => 12,
+            GamepadButton::Select => 13,
+            GamepadButton::Start => 14,
+            GamepadButton::LeftStick => 15,
+            GamepadButton::RightStick => 16,
+            GamepadButton::Steam => 27,
+            GamepadButton::QuickAccess => 28,
         }
     }
+
+    /// Keyboard key which the focus navigation also accepts in place of the button
+    fn button_key_equivalent(button: GamepadButton) -> Option<&'static str> {
+        match button {
+            GamepadButton::A => Some("Enter"),
+            GamepadButton::B => Some("Escape"),
+            GamepadButton::DPadUp => Some("ArrowUp"),
+            GamepadButton::DPadDown => Some("ArrowDown"),
+            GamepadButton::DPadLeft => Some("ArrowLeft"),
+            GamepadButton::DPadRight => Some("ArrowRight"),
+            _ => None,
+        }
+    }
+
+    fn fail(e: TestError) -> Feedback {
+        log::error!("{}", e);
+        Feedback::Error(e)
+    }
 }
 
 impl TestAdapter for HeadlessAdapter {
     fn element_click(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
-        // TODO better feedback
-        if let Some(tab) = self.select_tab(tab_s, true) {
-            /*if let Some(element) = self.select_element(&tab, element_s) {
-                match element.click() {
-                    Ok(_) => Feedback::Success,
-                    Err(e) => {
-                        log::error!("Failed to click on element {}: {}", element_s, e);
-                        Feedback::Error
-                    }
+        self.with_element(tab_s, element_s, |element| {
+            Self::click_feedback(element_s, Self::pointer_click(element, Input::MouseButton::Left, 1, None, 0))
+        })
+    }
+
+    fn element_double_click(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            Self::click_feedback(element_s, Self::pointer_click(element, Input::MouseButton::Left, 2, None, 0))
+        })
+    }
+
+    fn element_right_click(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            Self::click_feedback(element_s, Self::pointer_click(element, Input::MouseButton::Right, 1, None, 0))
+        })
+    }
+
+    fn element_click_at(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, x: f64, y: f64) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            Self::click_feedback(element_s, Self::pointer_click(element, Input::MouseButton::Left, 1, Some((x, y)), 0))
+        })
+    }
+
+    fn element_long_press(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, milliseconds: u64) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            Self::click_feedback(element_s, Self::pointer_click(element, Input::MouseButton::Left, 1, None, milliseconds))
+        })
+    }
+
+    fn element_hover(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            let result = Self::element_point(element, None)
+                .and_then(|point| Self::mouse_event(element.parent, Input::DispatchMouseEventTypeOption::MouseMoved, point, Input::MouseButton::None, 0, 0));
+            Self::gesture_feedback(element_s, "hover over", result)
+        })
+    }
+
+    fn element_drag_to(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, target_s: &ElementSelector) -> Feedback {
+        match self.select_tab(tab_s, true) {
+            Ok(tab) => {
+                let element = match self.select_element(&tab, tab_s, element_s) {
+                    Ok(element) => element,
+                    Err(e) => return Self::fail(e),
+                };
+                let target = match self.select_element(&tab, tab_s, target_s) {
+                    Ok(target) => target,
+                    Err(e) => return Self::fail(e),
+                };
+                let result = Self::element_point(&element, None)
+                    .and_then(|start| Ok((start, Self::element_point(&target, None)?)))
+                    .and_then(|(start, end)| Self::pointer_drag(&tab, start, end));
+                Self::gesture_feedback(element_s, "drag", result)
+            },
+            Err(e) => Self::fail(e),
+        }
+    }
+
+    fn element_drag_by(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, x: f64, y: f64) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            let result = Self::element_point(element, None)
+                .and_then(|start| Self::pointer_drag(element.parent, start, Point { x: start.x + x, y: start.y + y }));
+            Self::gesture_feedback(element_s, "drag", result)
+        })
+    }
+
+    fn element_swipe(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, x: f64, y: f64) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            let result = Self::element_point(element, None)
+                .and_then(|start| Self::touch_gesture(element.parent, start, Point { x: start.x + x, y: start.y + y }));
+            Self::gesture_feedback(element_s, "swipe", result)
+        })
+    }
+
+    fn element_tap(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            let result = Self::element_point(element, None)
+                .and_then(|point| Self::touch_gesture(element.parent, point, point));
+            Self::gesture_feedback(element_s, "tap", result)
+        })
+    }
+
+    fn element_wait(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        // nothing to do -- select_element already waits
+        self.with_element(tab_s, element_s, |_element| Feedback::Success)
+    }
+
+    fn element_wait_visible(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.wait_element_condition(tab_s, element_s, selector_js::visible_check(true), "be visible")
+    }
+
+    fn element_wait_hidden(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.wait_element_condition(tab_s, element_s, selector_js::visible_check(false), "be hidden")
+    }
+
+    fn element_wait_enabled(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.wait_element_condition(tab_s, element_s, selector_js::ENABLED_CHECK, "be enabled")
+    }
+
+    fn element_wait_text(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, text: &str, exact: bool) -> Feedback {
+        let condition = if exact {
+            format!("have text \"{}\"", text)
+        } else {
+            format!("contain text \"{}\"", text)
+        };
+        self.wait_element_condition(tab_s, element_s, &selector_js::text_check(text, exact), &condition)
+    }
+
+    fn element_focus(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            match element.focus() {
+                Ok(_) => Feedback::Success,
+                Err(e) => {
+                    log::error!("Failed to focus on element {}: {}", element_s, e);
+                    Feedback::Error(Self::adapter_error("focus on element", e))
                 }
-            } else {
-                log::error!("Failed to find element {}", element_s);
-                Feedback::Error
-            }*/
-            // FIXME element.click() doesn't actually click
-            let result = match element_s {
-                ElementSelector::CSS(css) => tab.evaluate(&format!("document.querySelector(\"{}\").click()", css), true),
-            };
-            match result {
+            }
+        })
+    }
+
+    fn element_scroll_to(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            match element.scroll_into_view() {
                 Ok(_) => Feedback::Success,
                 Err(e) => {
-                    log::error!("Failed to click on element {}: {}", element_s, e);
-                    Feedback::Error
+                    log::error!("Failed to scroll to element {}: {}", element_s, e);
+                    Feedback::Error(Self::adapter_error("scroll to element", e))
                 }
             }
-        } else {
-            log::error!("Failed to find tab {}", tab_s);
-            Feedback::Error
-        }
+        })
     }
 
-    fn element_wait(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
-        // TODO better feedback
-        if let Some(tab) = self.select_tab(tab_s, true) {
-            if let Some(_element) = self.select_element(&tab, element_s) {
-                // nothing to do -- select_element already waits
-                Feedback::Success
-            } else {
-                log::error!("Failed to find element {}", element_s);
-                Feedback::Error
+    fn element_type_text(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, text: &str) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            match element.focus().and_then(|_| element.parent.send_character(text)) {
+                Ok(_) => Feedback::Success,
+                Err(e) => {
+                    log::error!("Failed to type text into element {}: {}", element_s, e);
+                    Feedback::Error(Self::adapter_error("type text into element", e))
+                }
             }
-        } else {
-            log::error!("Failed to find tab {}", tab_s);
-            Feedback::Error
-        }
+        })
     }
 
-    fn element_focus(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
-        // TODO better feedback
-        if let Some(tab) = self.select_tab(tab_s, true) {
-            if let Some(element) = self.select_element(&tab, element_s) {
-                match element.focus() {
-                    Ok(_) => Feedback::Success,
-                    Err(e) => {
-                        log::error!("Failed to click on element {}: {}", element_s, e);
-                        Feedback::Error
-                    }
+    fn element_clear(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            // select everything then delete it like a user would, so frameworks see the input events
+            let select_all = "function() {
+                this.focus();
+                if (typeof this.select === 'function') {
+                    this.select();
+                } else {
+                    document.getSelection().selectAllChildren(this);
+                }
+            }";
+            match element.call_js_fn(select_all, Vec::new(), false).and_then(|_| element.parent.press_key("Backspace")) {
+                Ok(_) => Feedback::Success,
+                Err(e) => {
+                    log::error!("Failed to clear element {}: {}", element_s, e);
+                    Feedback::Error(Self::adapter_error("clear element", e))
                 }
-            } else {
-                log::error!("Failed to find element {}", element_s);
-                Feedback::Error
             }
-        } else {
-            log::error!("Failed to find tab {}", tab_s);
-            Feedback::Error
+        })
+    }
+
+    fn element_value(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            match element.get_inner_text() {
+                Ok(t) => Feedback::Value(t.into()),
+                Err(e) => {
+                    log::error!("Failed to get inner text value of element {}: {}", element_s, e);
+                    Feedback::Error(Self::adapter_error("get inner text of element", e))
+                }
+            }
+        })
+    }
+
+    fn element_count(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
+        match self.select_tab(tab_s, true) {
+            Ok(tab) => match Self::evaluate_script(&tab, &selector_js::count(tab_s, element_s), self.timeout) {
+                Ok(count) => Feedback::Value(count),
+                Err(e) => {
+                    log::error!("Failed to count elements {} on tab {}: {}", element_s, tab_s, e);
+                    Feedback::Error(e)
+                }
+            },
+            Err(e) => Self::fail(e),
         }
     }
 
-    fn element_scroll_to(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
-        // TODO better feedback
-        if let Some(tab) = self.select_tab(tab_s, true) {
-            if let Some(element) = self.select_element(&tab, element_s) {
-                match element.scroll_into_view() {
+    fn element_attribute(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, attribute: &str) -> Feedback {
+        self.with_element(tab_s, element_s, |element| {
+            match element.get_attributes() {
+                // attributes are a flattened list of name, value pairs
+                Ok(Some(attrs)) => Feedback::Value(
+                    attrs.chunks(2)
+                        .find(|pair| pair[0].eq_ignore_ascii_case(attribute))
+                        .map(|pair| pair.get(1).cloned().unwrap_or_default().into())
+                        .unwrap_or(serde_json::Value::Null)
+                ),
+                Ok(None) => Feedback::Value(serde_json::Value::Null),
+                Err(e) => {
+                    log::error!("Failed to get attributes of element {}: {}", element_s, e);
+                    Feedback::Error(Self::adapter_error("get attributes of element", e))
+                }
+            }
+        })
+    }
+
+    fn key_press(&mut self, tab_s: &TabSelector, key: &str, modifiers: &[KeyModifier]) -> Feedback {
+        match self.select_tab(tab_s, true) {
+            Ok(tab) => {
+                let modifiers: Vec<ModifierKey> = modifiers.iter().map(|m| match m {
+                    KeyModifier::Alt => ModifierKey::Alt,
+                    KeyModifier::Ctrl => ModifierKey::Ctrl,
+                    KeyModifier::Meta => ModifierKey::Meta,
+                    KeyModifier::Shift => ModifierKey::Shift,
+                }).collect();
+                match tab.press_key_with_modifiers(key, Some(&modifiers)) {
                     Ok(_) => Feedback::Success,
                     Err(e) => {
-                        log::error!("Failed to click on element {}: {}", element_s, e);
-                        Feedback::Error
+                        log::error!("Failed to press key {} on tab {}: {}", key, tab_s, e);
+                        Feedback::Error(Self::adapter_error(&format!("press key {}", key), e))
                     }
                 }
-            } else {
-                log::error!("Failed to find element {}", element_s);
-                Feedback::Error
-            }
-        } else {
-            log::error!("Failed to find tab {}", tab_s);
-            Feedback::Error
+            },
+            Err(e) => Self::fail(e),
         }
     }
 
-    fn element_value(&mut self, tab_s: &TabSelector, element_s: &ElementSelector) -> Feedback {
-        if let Some(tab) = self.select_tab(tab_s, true) {
-            if let Some(element) = self.select_element(&tab, element_s) {
-                match element.get_inner_text() {
-                    Ok(t) => Feedback::Value(t.into()),
-                    Err(e) => {
-                        log::error!("Failed to get inner text value of element {}: {}", element_s, e);
-                        Feedback::Error
+    fn key_sequence(&mut self, tab_s: &TabSelector, keys: &[String]) -> Feedback {
+        match self.select_tab(tab_s, true) {
+            Ok(tab) => {
+                for key in keys {
+                    if let Err(e) = tab.press_key(key) {
+                        log::error!("Failed to press key {} on tab {}: {}", key, tab_s, e);
+                        return Feedback::Error(Self::adapter_error(&format!("press key {}", key), e));
                     }
                 }
-            } else {
-                log::error!("Failed to find element {}", element_s);
-                Feedback::Error
-            }
-        } else {
-            log::error!("Failed to find tab {}", tab_s);
-            Feedback::Error
+                Feedback::Success
+            },
+            Err(e) => Self::fail(e),
         }
     }
 
-    fn element_attribute(&mut self, tab_s: &TabSelector, element_s: &ElementSelector, _attribute: &str) -> Feedback {
-        if let Some(tab) = self.select_tab(tab_s, true) {
-            if let Some(element) = self.select_element(&tab, element_s) {
-                match element.get_attributes() {
-                    Ok(Some(_attrs)) => {
-                        // TODO
-                        //attrs.get(attribute).map(|x| x.into()).unwrap_or(serde_json::Value::Null)
-                        Feedback::Unsupported
+    fn gamepad_button(&mut self, tab_s: &TabSelector, button: GamepadButton) -> Feedback {
+        match self.select_tab(tab_s, true) {
+            Ok(tab) => {
+                // Steam's gamepad UI delivers buttons to the focused element as vgp_* events
+                let script = format!("(() => {{
+                    const target = document.activeElement || document.body;
+                    const detail = {{ button: {}, is_repeat: false }};
+                    const handled = !target.dispatchEvent(new CustomEvent('vgp_onbuttondown', {{ bubbles: true, cancelable: true, detail }}));
+                    target.dispatchEvent(new CustomEvent('vgp_onbuttonup', {{ bubbles: true, cancelable: true, detail }}));
+                    return handled;
+                }})()", Self::steam_button_code(button));
+                match Self::evaluate_script(&tab, &selector_js::in_frame(tab_s, &script), self.timeout) {
+                    Ok(serde_json::Value::Bool(true)) => Feedback::Success,
+                    Ok(_) => {
+                        // unhandled, so fall back to the equivalent key for focus navigation
+                        if let Some(key) = Self::button_key_equivalent(button) {
+                            match tab.press_key(key) {
+                                Ok(_) => Feedback::Success,
+                                Err(e) => {
+                                    log::error!("Failed to press button {:?} on tab {}: {}", button, tab_s, e);
+                                    Feedback::Error(Self::adapter_error(&format!("press button {:?}", button), e))
+                                }
+                            }
+                        } else {
+                            Feedback::Success
+                        }
                     },
-                    Ok(None) => Feedback::Value(serde_json::Value::Null),
                     Err(e) => {
-                        log::error!("Failed to get attributes of element {}: {}", element_s, e);
-                        Feedback::Error
+                        log::error!("Failed to press button {:?} on tab {}: {}", button, tab_s, e);
+                        Feedback::Error(e)
                     }
                 }
-            } else {
-                log::error!("Failed to find element {}", element_s);
-                Feedback::Error
-            }
-        } else {
-            log::error!("Failed to find tab {}", tab_s);
-            Feedback::Error
+            },
+            Err(e) => Self::fail(e),
         }
     }
 
     fn wait(&mut self, tab_s: &TabSelector, milliseconds: u64) -> Feedback {
-        // TODO better feedback
-        if let Some(_tab) = self.select_tab(tab_s, true) {
-            let duration = std::time::Duration::from_millis(milliseconds);
-            std::thread::sleep(duration);
-            Feedback::Success
-        } else {
-            log::error!("Failed to find tab {}", tab_s);
-            Feedback::Error
+        match self.select_tab(tab_s, true) {
+            Ok(_tab) => {
+                let duration = std::time::Duration::from_millis(milliseconds);
+                std::thread::sleep(duration);
+                Feedback::Success
+            },
+            Err(e) => Self::fail(e),
         }
     }
 
     fn evaluate(&mut self, tab_s: &TabSelector, script: &str) -> Feedback {
-        if let Some(tab) = self.select_tab(tab_s, true) {
-            match tab.evaluate(script, true) {
-                Ok(result) => Feedback::Value(result.value.unwrap_or(serde_json::Value::Null)),
+        match self.select_tab(tab_s, true) {
+            Ok(tab) => match Self::evaluate_script(&tab, &selector_js::in_frame(tab_s, script), self.timeout) {
+                Ok(value) => Feedback::Value(value),
                 Err(e) => {
                     log::error!("Failed to evaluate script on tab {}: {}", tab_s, e);
-                    Feedback::Error
+                    Feedback::Error(e)
                 }
+            },
+            Err(e) => Self::fail(e),
+        }
+    }
+
+    fn wait_script(&mut self, tab_s: &TabSelector, script: &str) -> Feedback {
+        match self.select_tab(tab_s, true) {
+            Ok(tab) => match Self::wait_for_script(&tab, &selector_js::in_frame(tab_s, script), self.timeout, "wait for script to be truthy") {
+                Ok(_) => Feedback::Success,
+                Err(e) => Self::fail(e),
+            },
+            Err(e) => Self::fail(e),
+        }
+    }
+
+    fn wait_network_idle(&mut self, tab_s: &TabSelector, idle_milliseconds: u64) -> Feedback {
+        let tab = match self.select_tab(tab_s, true) {
+            Ok(tab) => tab,
+            Err(e) => return Self::fail(e),
+        };
+        if let Err(e) = tab.call_method(Network::Enable { max_total_buffer_size: None, max_resource_buffer_size: None, max_post_data_size: None }) {
+            return Self::fail(Self::adapter_error("enable network events", e));
+        }
+        // requests in flight and the time of the last network activity
+        // (requests sent before the listener is attached are only seen when they finish)
+        let activity = Arc::new(Mutex::new((HashSet::new(), std::time::Instant::now())));
+        let tracker = activity.clone();
+        let listener = tab.add_event_listener(Arc::new(move |event: &Event| {
+            if let Ok(mut activity) = tracker.lock() {
+                match event {
+                    Event::NetworkRequestWillBeSent(e) => { activity.0.insert(e.params.request_id.clone()); },
+                    Event::NetworkLoadingFinished(e) => { activity.0.remove(&e.params.request_id); },
+                    Event::NetworkLoadingFailed(e) => { activity.0.remove(&e.params.request_id); },
+                    _ => return,
+                }
+                activity.1 = std::time::Instant::now();
             }
-        } else {
-            log::error!("Failed to find tab {}", tab_s);
-            Feedback::Error
+        }));
+        let listener = match listener {
+            Ok(listener) => listener,
+            Err(e) => {
+                let error = Self::adapter_error("listen for network events", e);
+                if let Err(e) = tab.call_method(Network::Disable(None)) {
+                    log::warn!("Failed to disable network events in tab {}: {}", tab_s, e);
+                }
+                return Self::fail(error);
+            },
+        };
+        let idle = std::time::Duration::from_millis(idle_milliseconds);
+        let start = std::time::Instant::now();
+        let result = loop {
+            let is_idle = activity.lock()
+                .map(|activity| activity.0.is_empty() && activity.1.elapsed() >= idle)
+                .unwrap_or(false);
+            if is_idle {
+                break Feedback::Success;
+            }
+            if start.elapsed() > self.timeout {
+                break Self::fail(TestError::Timeout {
+                    operation: format!("wait for network idle in {}", tab_s),
+                    milliseconds: Some(self.timeout.as_millis() as u64),
+                });
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+        if let Err(e) = tab.remove_event_listener(&listener) {
+            log::warn!("Failed to remove network listener from tab {}: {}", tab_s, e);
+        }
+        // stop streaming network events to every later command
+        if let Err(e) = tab.call_method(Network::Disable(None)) {
+            log::warn!("Failed to disable network events in tab {}: {}", tab_s, e);
         }
+        result
+    }
+
+    fn wait_load(&mut self, tab_s: &TabSelector) -> Feedback {
+        match self.select_tab(tab_s, true) {
+            Ok(tab) => {
+                let script = selector_js::in_frame(tab_s, "document.readyState === 'complete'");
+                match Self::wait_for_script(&tab, &script, self.timeout, &format!("wait for {} to load", tab_s)) {
+                    Ok(_) => Feedback::Success,
+                    Err(e) => Self::fail(e),
+                }
+            },
+            Err(e) => Self::fail(e),
+        }
+    }
+
+    fn wait_tab(&mut self, tab_s: &TabSelector) -> Feedback {
+        // nothing to do -- select_tab already waits
+        match self.select_tab(tab_s, true) {
+            Ok(_) => Feedback::Success,
+            Err(e) => Self::fail(e),
+        }
+    }
+
+    fn set_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
     }
 }
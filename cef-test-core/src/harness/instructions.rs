@@ -12,6 +12,18 @@ pub struct TestAssert {
     pub context: TabSelector,
     /// Test assertion
     pub assertion: GeneralAssertType,
+    /// Time limit, if different from the default
+    pub timeout: Option<std::time::Duration>,
+    /// Retry the assertion until it passes, instead of checking once
+    pub eventually: Option<Polling>,
+}
+
+/// Retry behaviour of an assertion which is expected to pass eventually
+pub struct Polling {
+    /// Time allowed for the assertion to pass, if different from the instruction's time limit
+    pub timeout: Option<std::time::Duration>,
+    /// Pause between attempts
+    pub interval: std::time::Duration,
 }
 
 /// Test assertion information
@@ -22,6 +34,20 @@ pub enum GeneralAssertType {
     Tab(TabAssert),
 }
 
+impl GeneralAssertType {
+    /// Comparison which the assertion checks
+    pub fn comparison(&self) -> &Comparison {
+        match self {
+            Self::Element(elem) => match &elem.assert {
+                ElementAssertionType::Value(comparison) => comparison,
+                ElementAssertionType::Attribute { comparison, .. } => comparison,
+                ElementAssertionType::Count(comparison) => comparison,
+            },
+            Self::Tab(TabAssert::Evaluate { comparison, .. }) => comparison,
+        }
+    }
+}
+
 /// Element assertion
 pub struct ElementAssert {
     /// Element to target
@@ -41,7 +67,8 @@ pub enum ElementAssertionType {
         /// Assertion comparison mode
         comparison: Comparison
     },
-
+    /// Assert number of elements matching the selector
+    Count(Comparison),
 }
 
 /// Assertion operations
@@ -65,11 +92,12 @@ pub enum Comparison {
     TextEquals(String),
     /// Assert contains expected string
     TextContains(String),
+    /// Assert text matches regex pattern
+    TextMatches(String),
     /// Assert == expected value
     Equals(serde_json::Value),
     /// Assert actual != expected value
     NotEquals(serde_json::Value),
-    /*
     /// Assert actual < expected value
     LessThan(serde_json::Value),
     /// Assert actual <= expected value
@@ -78,14 +106,45 @@ pub enum Comparison {
     GreaterThan(serde_json::Value),
     /// Assert actual >= expected value
     GreaterThanEquals(serde_json::Value),
-    */
+    /// Assert actual number is within tolerance of expected number
+    ApproxEquals {
+        /// Expected number
+        expected: f64,
+        /// Maximum allowed difference
+        tolerance: f64,
+    },
+    /// Assert actual is equal to one of the expected values
+    OneOf(Vec<serde_json::Value>),
+    /// Assert on the length of an array or string
+    Length(Box<Comparison>),
+    /// Assert on the value selected by a JSON Pointer (e.g. `/battery/percent`)
+    Pointer {
+        /// JSON Pointer
+        pointer: String,
+        /// Comparison for the selected value
+        comparison: Box<Comparison>,
+    },
+    /// Assert on the value selected by a JSONPath query (e.g. `$.battery.percent`).
+    /// A query selecting multiple values is compared as an array of those values.
+    JsonPath {
+        /// JSONPath query
+        path: String,
+        /// Comparison for the selected value
+        comparison: Box<Comparison>,
+    },
+    /// Assert all comparisons are satisfied
+    All(Vec<Comparison>),
+    /// Assert at least one comparison is satisfied
+    Any(Vec<Comparison>),
+    /// Assert comparison is not satisfied
+    Not(Box<Comparison>),
 }
 
 impl Comparison {
     /// Compare actual value
     pub fn compare(&self, value: Option<&serde_json::Value>) -> bool {
         match self {
-            Self::Exists => !value.is_none(),
+            Self::Exists => value.map(|v| !v.is_null()).unwrap_or(false),
             Self::ExistsNotEmpty => {
                 if let Some(value) = value {
                     if let Some(s) = value.as_str() {
@@ -97,6 +156,12 @@ impl Comparison {
                     false
                 }
             },
+            Self::Length(comparison) => comparison.compare(Self::length(value).as_ref()),
+            Self::Pointer { pointer, comparison } => comparison.compare(value.and_then(|v| v.pointer(pointer))),
+            Self::JsonPath { path, comparison } => comparison.compare(Self::query(value, path).as_ref()),
+            Self::All(comparisons) => comparisons.iter().all(|c| c.compare(value)),
+            Self::Any(comparisons) => comparisons.iter().any(|c| c.compare(value)),
+            Self::Not(comparison) => !comparison.compare(value),
             _ => {
                 let value = value.unwrap_or(&serde_json::Value::Null);
                 match self {
@@ -114,42 +179,192 @@ impl Comparison {
                             false
                         }
                     },
+                    Self::TextMatches(pattern) => {
+                        if let Some(actual) = value.as_str() {
+                            match regex::Regex::new(pattern) {
+                                Ok(pattern) => pattern.is_match(actual),
+                                Err(e) => {
+                                    log::warn!("Invalid regex pattern `{}`: {}", pattern, e);
+                                    false
+                                }
+                            }
+                        } else {
+                            false
+                        }
+                    },
                     Self::Equals(expected) => value == expected,
                     Self::NotEquals(expected) => value != expected,
+                    Self::LessThan(expected) => Self::order(value, expected).map(|o| o.is_lt()).unwrap_or(false),
+                    Self::LessThanEquals(expected) => Self::order(value, expected).map(|o| o.is_le()).unwrap_or(false),
+                    Self::GreaterThan(expected) => Self::order(value, expected).map(|o| o.is_gt()).unwrap_or(false),
+                    Self::GreaterThanEquals(expected) => Self::order(value, expected).map(|o| o.is_ge()).unwrap_or(false),
+                    Self::ApproxEquals { expected, tolerance } => Self::as_number(value).map(|actual| (actual - expected).abs() <= *tolerance).unwrap_or(false),
+                    Self::OneOf(expected) => expected.contains(value),
 
                     Self::Exists => unreachable!(),
                     Self::ExistsNotEmpty => unreachable!(),
+                    Self::Length(_) => unreachable!(),
+                    Self::Pointer { .. } => unreachable!(),
+                    Self::JsonPath { .. } => unreachable!(),
+                    Self::All(_) => unreachable!(),
+                    Self::Any(_) => unreachable!(),
+                    Self::Not(_) => unreachable!(),
                 }
             }
         }
     }
 
+    /// Order actual value relative to expected value.
+    /// Numbers and numeric strings are ordered numerically, other strings lexicographically.
+    fn order(actual: &serde_json::Value, expected: &serde_json::Value) -> Option<std::cmp::Ordering> {
+        match (Self::as_number(actual), Self::as_number(expected)) {
+            (Some(actual), Some(expected)) => actual.partial_cmp(&expected),
+            _ => match (actual.as_str(), expected.as_str()) {
+                (Some(actual), Some(expected)) => Some(actual.cmp(expected)),
+                _ => None,
+            }
+        }
+    }
+
+    fn as_number(value: &serde_json::Value) -> Option<f64> {
+        match value {
+            serde_json::Value::Number(n) => n.as_f64(),
+            serde_json::Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn length(value: Option<&serde_json::Value>) -> Option<serde_json::Value> {
+        match value? {
+            serde_json::Value::Array(items) => Some(items.len().into()),
+            serde_json::Value::String(s) => Some(s.chars().count().into()),
+            _ => None,
+        }
+    }
+
+    fn query(value: Option<&serde_json::Value>, path: &str) -> Option<serde_json::Value> {
+        let path = match serde_json_path::JsonPath::parse(path) {
+            Ok(path) => path,
+            Err(e) => {
+                log::warn!("Invalid JSONPath `{}`: {}", path, e);
+                return None;
+            }
+        };
+        let mut nodes = path.query(value?).all();
+        match nodes.len() {
+            0 => None,
+            1 => Some(nodes.remove(0).clone()),
+            _ => Some(nodes.into_iter().cloned().collect()),
+        }
+    }
+
+    /// Display-friendly representation of only the failing parts of the assertion,
+    /// or None if the assertion is satisfied
+    pub fn pseudocode_failure(&self, value: Option<&serde_json::Value>) -> Option<String> {
+        if self.compare(value) {
+            return None;
+        }
+        let failure = match self {
+            Self::All(comparisons) => comparisons.iter().find_map(|c| c.pseudocode_failure(value)),
+            Self::Any(comparisons) => Some(comparisons.iter().filter_map(|c| c.pseudocode_failure(value)).collect::<Vec<_>>().join(" and ")),
+            Self::Length(comparison) => comparison.pseudocode_failure(Self::length(value).as_ref())
+                .map(|f| format!("{} (length)", f)),
+            Self::Pointer { pointer, comparison } => comparison.pseudocode_failure(value.and_then(|v| v.pointer(pointer)))
+                .map(|f| format!("{} (at {})", f, pointer)),
+            Self::JsonPath { path, comparison } => comparison.pseudocode_failure(Self::query(value, path).as_ref())
+                .map(|f| format!("{} (at {})", f, path)),
+            _ => None,
+        };
+        Some(failure.filter(|f| !f.is_empty()).unwrap_or_else(|| self.pseudocode_assert(value)))
+    }
+
     /// Display-friendly representation of the assertion with actual and expected values
     pub fn pseudocode_assert(&self, value: Option<&serde_json::Value>) -> String {
-        match value {
-            Some(value) => {
-                match self {
-                    Self::Exists => format!("{} must exist", value),
-                    Self::ExistsNotEmpty => format!("{} must exist", value),
-                    Self::TextEquals(expected) => format!("\"{}\" must equal \"{}\"", value, expected),
-                    Self::TextContains(expected) => format!("\"{}\" must contain \"{}\"", value, expected),
-                    Self::Equals(expected) => format!("{} == {}", value, expected),
-                    Self::NotEquals(expected) => format!("{} != {}", value, expected),
-                }
-            },
-            None => {
-                let value = serde_json::Value::Null;
-                match self {
-                    Self::Exists => format!("None must exist (contradiction!)"),
-                    Self::ExistsNotEmpty => format!("None must exist (contradiction!)"),
-                    Self::TextEquals(expected) => format!("\"{}\" must equal \"{}\"", value, expected),
-                    Self::TextContains(expected) => format!("\"{}\" must contain \"{}\"", value, expected),
-                    Self::Equals(expected) => format!("{} == {}", value, expected),
-                    Self::NotEquals(expected) => format!("{} != {}", value, expected),
+        match self {
+            Self::Length(comparison) => format!("{} (length)", comparison.pseudocode_assert(Self::length(value).as_ref())),
+            Self::Pointer { pointer, comparison } => format!("{} (at {})", comparison.pseudocode_assert(value.and_then(|v| v.pointer(pointer))), pointer),
+            Self::JsonPath { path, comparison } => format!("{} (at {})", comparison.pseudocode_assert(Self::query(value, path).as_ref()), path),
+            Self::All(comparisons) => format!("({})", comparisons.iter().map(|c| c.pseudocode_assert(value)).collect::<Vec<_>>().join(" && ")),
+            Self::Any(comparisons) => format!("({})", comparisons.iter().map(|c| c.pseudocode_assert(value)).collect::<Vec<_>>().join(" || ")),
+            Self::Not(comparison) => format!("!({})", comparison.pseudocode_assert(value)),
+            _ => match value {
+                Some(value) => {
+                    // strings already display with quotes
+                    let text = value.as_str().map(|s| s.to_owned()).unwrap_or_else(|| value.to_string());
+                    match self {
+                        Self::Exists => format!("{} must exist", value),
+                        Self::ExistsNotEmpty => format!("{} must exist", value),
+                        Self::TextEquals(expected) => format!("\"{}\" must equal \"{}\"", text, expected),
+                        Self::TextContains(expected) => format!("\"{}\" must contain \"{}\"", text, expected),
+                        Self::TextMatches(pattern) => format!("\"{}\" must match /{}/", text, pattern),
+                        Self::Equals(expected) => format!("{} == {}", value, expected),
+                        Self::NotEquals(expected) => format!("{} != {}", value, expected),
+                        Self::LessThan(expected) => format!("{} < {}", value, expected),
+                        Self::LessThanEquals(expected) => format!("{} <= {}", value, expected),
+                        Self::GreaterThan(expected) => format!("{} > {}", value, expected),
+                        Self::GreaterThanEquals(expected) => format!("{} >= {}", value, expected),
+                        Self::ApproxEquals { expected, tolerance } => format!("{} ~= {} (±{})", value, expected, tolerance),
+                        Self::OneOf(expected) => format!("{} in {}", value, serde_json::Value::Array(expected.clone())),
+                        Self::Length(_) => unreachable!(),
+                        Self::Pointer { .. } => unreachable!(),
+                        Self::JsonPath { .. } => unreachable!(),
+                        Self::All(_) => unreachable!(),
+                        Self::Any(_) => unreachable!(),
+                        Self::Not(_) => unreachable!(),
+                    }
+                },
+                None => {
+                    let value = serde_json::Value::Null;
+                    match self {
+                        Self::Exists => "None must exist (contradiction!)".to_owned(),
+                        Self::ExistsNotEmpty => "None must exist (contradiction!)".to_owned(),
+                        Self::TextEquals(expected) => format!("\"{}\" must equal \"{}\"", value, expected),
+                        Self::TextContains(expected) => format!("\"{}\" must contain \"{}\"", value, expected),
+                        Self::TextMatches(pattern) => format!("\"{}\" must match /{}/", value, pattern),
+                        Self::Equals(expected) => format!("{} == {}", value, expected),
+                        Self::NotEquals(expected) => format!("{} != {}", value, expected),
+                        Self::LessThan(expected) => format!("{} < {}", value, expected),
+                        Self::LessThanEquals(expected) => format!("{} <= {}", value, expected),
+                        Self::GreaterThan(expected) => format!("{} > {}", value, expected),
+                        Self::GreaterThanEquals(expected) => format!("{} >= {}", value, expected),
+                        Self::ApproxEquals { expected, tolerance } => format!("{} ~= {} (±{})", value, expected, tolerance),
+                        Self::OneOf(expected) => format!("{} in {}", value, serde_json::Value::Array(expected.clone())),
+                        Self::Length(_) => unreachable!(),
+                        Self::Pointer { .. } => unreachable!(),
+                        Self::JsonPath { .. } => unreachable!(),
+                        Self::All(_) => unreachable!(),
+                        Self::Any(_) => unreachable!(),
+                        Self::Not(_) => unreachable!(),
+                    }
                 }
             }
         }
+    }
+}
 
+impl std::fmt::Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exists => write!(f, "exists"),
+            Self::ExistsNotEmpty => write!(f, "exists and is not empty"),
+            Self::TextEquals(expected) => write!(f, "text equal to \"{}\"", expected),
+            Self::TextContains(expected) => write!(f, "text containing \"{}\"", expected),
+            Self::TextMatches(pattern) => write!(f, "text matching /{}/", pattern),
+            Self::Equals(expected) => write!(f, "== {}", expected),
+            Self::NotEquals(expected) => write!(f, "!= {}", expected),
+            Self::LessThan(expected) => write!(f, "< {}", expected),
+            Self::LessThanEquals(expected) => write!(f, "<= {}", expected),
+            Self::GreaterThan(expected) => write!(f, "> {}", expected),
+            Self::GreaterThanEquals(expected) => write!(f, ">= {}", expected),
+            Self::ApproxEquals { expected, tolerance } => write!(f, "~= {} (±{})", expected, tolerance),
+            Self::OneOf(expected) => write!(f, "one of {}", serde_json::Value::Array(expected.clone())),
+            Self::Length(comparison) => write!(f, "length {}", comparison),
+            Self::Pointer { pointer, comparison } => write!(f, "{} {}", pointer, comparison),
+            Self::JsonPath { path, comparison } => write!(f, "{} {}", path, comparison),
+            Self::All(comparisons) => write!(f, "all of [{}]", comparisons.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", ")),
+            Self::Any(comparisons) => write!(f, "any of [{}]", comparisons.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", ")),
+            Self::Not(comparison) => write!(f, "not ({})", comparison),
+        }
     }
 }
 
@@ -159,6 +374,8 @@ pub struct TestOp {
     pub context: TabSelector,
     /// Test operation
     pub op: GeneralOpType,
+    /// Time limit, if different from the default
+    pub timeout: Option<std::time::Duration>,
 }
 
 /// Element selection mode
@@ -166,12 +383,110 @@ pub struct TestOp {
 pub enum ElementSelector {
     /// Use CSS selector syntax
     CSS(String),
+    /// Use XPath syntax
+    XPath(String),
+    /// Select by exact visible text (innermost element)
+    Text(String),
+    /// Select by partial visible text (innermost element)
+    TextContains(String),
+    /// Select by ARIA role and, optionally, accessible name
+    Role {
+        /// ARIA role (explicit or implicit)
+        role: String,
+        /// Accessible name
+        name: Option<String>,
+    },
+    /// Select by attribute value (e.g. `data-testid`)
+    Attribute {
+        /// Attribute name
+        attribute: String,
+        /// Attribute value
+        value: String,
+    },
+    /// Select by placeholder text
+    Placeholder(String),
+    /// Select form control by its label text
+    Label(String),
+    /// Select first DOM node rendered by a React component
+    React {
+        /// Component display name
+        component: String,
+        /// Props which the component must have (objects match as subsets)
+        props: serde_json::Map<String, serde_json::Value>,
+    },
+    /// Select the nth (zero-indexed) match of another selector
+    Nth {
+        /// Selector to pick from
+        selector: Box<ElementSelector>,
+        /// Match index
+        index: usize,
+    },
+    /// Select the last match of another selector
+    Last(Box<ElementSelector>),
+    /// Select matches of child found inside matches of parent
+    Within {
+        /// Selector of the enclosing element
+        parent: Box<ElementSelector>,
+        /// Selector to search for inside the parent
+        child: Box<ElementSelector>,
+    },
+    /// Select the parent element of matches of another selector
+    Parent(Box<ElementSelector>),
+    /// Select siblings of matches of another selector
+    Sibling {
+        /// Selector of the element whose siblings to search
+        of: Box<ElementSelector>,
+        /// Selector the sibling must match
+        sibling: Box<ElementSelector>,
+    },
+    /// Select matches of another selector which contain text
+    WithText {
+        /// Selector to filter
+        selector: Box<ElementSelector>,
+        /// Text the element must contain
+        text: String,
+    },
+    /// Select matches of child inside the document of an iframe (same-origin only)
+    Frame {
+        /// Selector of the iframe element
+        frame: Box<ElementSelector>,
+        /// Selector to search for inside the iframe
+        child: Box<ElementSelector>,
+    },
+    /// Select matches of child inside the (open) shadow root of a host element
+    Shadow {
+        /// Selector of the shadow host element
+        host: Box<ElementSelector>,
+        /// Selector to search for inside the shadow root
+        child: Box<ElementSelector>,
+    },
+    /// Use CSS selector syntax, searching inside all open shadow roots and same-origin iframes
+    DeepCSS(String),
 }
 
 impl std::fmt::Display for ElementSelector {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::CSS(pattern) => write!(f, "Element[css~`{}`]", pattern),
+            Self::XPath(pattern) => write!(f, "Element[xpath~`{}`]", pattern),
+            Self::Text(text) => write!(f, "Element[text==`{}`]", text),
+            Self::TextContains(text) => write!(f, "Element[text*=`{}`]", text),
+            Self::Role { role, name: Some(name) } => write!(f, "Element[role==`{}`, name==`{}`]", role, name),
+            Self::Role { role, name: None } => write!(f, "Element[role==`{}`]", role),
+            Self::Attribute { attribute, value } => write!(f, "Element[@{}==`{}`]", attribute, value),
+            Self::Placeholder(text) => write!(f, "Element[placeholder==`{}`]", text),
+            Self::Label(text) => write!(f, "Element[label==`{}`]", text),
+            Self::React { component, props } if props.is_empty() => write!(f, "Element[react==`{}`]", component),
+            Self::React { component, props } => write!(f, "Element[react==`{}`, props~`{}`]", component, serde_json::Value::Object(props.clone())),
+            Self::Nth { selector, index } => write!(f, "{}[{}]", selector, index),
+            Self::Last(selector) => write!(f, "{}[last]", selector),
+            Self::Within { parent, child } => write!(f, "{} > {}", parent, child),
+            Self::Parent(selector) => write!(f, "{}.parent", selector),
+            Self::Sibling { of, sibling } => write!(f, "{} ~ {}", of, sibling),
+            Self::WithText { selector, text } => write!(f, "{}[text*=`{}`]", selector, text),
+            Self::Frame { frame, child } => write!(f, "Frame[{}] > {}", frame, child),
+            Self::Shadow { host, child } => write!(f, "Shadow[{}] > {}", host, child),
+            Self::DeepCSS(pattern) => write!(f, "Element[deep-css~`{}`]", pattern),
         }
     }
 }
@@ -188,6 +503,13 @@ pub enum TabSelector {
     UrlRegex(String),
     /// Select by tab identifier
     Id(String),
+    /// Select an iframe within a tab (same-origin only)
+    Frame {
+        /// Tab (or frame) containing the iframe
+        tab: Box<TabSelector>,
+        /// Selector of the iframe element
+        frame: ElementSelector,
+    },
 }
 
 impl std::fmt::Display for TabSelector {
@@ -198,6 +520,7 @@ impl std::fmt::Display for TabSelector {
             Self::Url(url) => write!(f, "Tab[url==`{}`]", url),
             Self::UrlRegex(url) => write!(f, "Tab[url~=`{}`]", url),
             Self::Id(id) => write!(f, "Tab[id==`{}`]", id),
+            Self::Frame { tab, frame } => write!(f, "{} > Frame[{}]", tab, frame),
         }
     }
 }
@@ -216,6 +539,79 @@ pub enum TabOpType {
     Sleep(u64),
     /// Execute Javascript in the global tab context
     Evaluate(String),
+    /// Press a key, with modifier keys held down
+    KeyPress {
+        /// Key name (e.g. `Enter`, `a`, `ArrowDown`)
+        key: String,
+        /// Modifier keys
+        modifiers: Vec<KeyModifier>,
+    },
+    /// Press keys one after another
+    KeySequence(Vec<String>),
+    /// Press a controller button
+    GamepadButton(GamepadButton),
+    /// Wait for Javascript expression to be truthy
+    WaitForScript(String),
+    /// Wait for no network requests for a period, in milliseconds
+    WaitForNetworkIdle(u64),
+    /// Wait for page to finish loading
+    WaitForLoad,
+    /// Wait for tab to exist
+    WaitForTab,
+}
+
+/// Keyboard modifier key
+#[derive(Clone, Copy, Debug)]
+pub enum KeyModifier {
+    /// Alt key
+    Alt,
+    /// Control key
+    Ctrl,
+    /// Meta (Windows/Command) key
+    Meta,
+    /// Shift key
+    Shift,
+}
+
+/// Controller button
+#[derive(Clone, Copy, Debug)]
+pub enum GamepadButton {
+    /// A (confirm) button
+    A,
+    /// B (cancel/back) button
+    B,
+    /// X button
+    X,
+    /// Y button
+    Y,
+    /// D-pad up
+    DPadUp,
+    /// D-pad down
+    DPadDown,
+    /// D-pad left
+    DPadLeft,
+    /// D-pad right
+    DPadRight,
+    /// Left bumper (L1)
+    LeftBumper,
+    /// Right bumper (R1)
+    RightBumper,
+    /// Left trigger (L2)
+    LeftTrigger,
+    /// Right trigger (R2)
+    RightTrigger,
+    /// Select (view) button
+    Select,
+    /// Start (menu) button
+    Start,
+    /// Left stick click (L3)
+    LeftStick,
+    /// Right stick click (R3)
+    RightStick,
+    /// Steam (guide) button
+    Steam,
+    /// Quick access menu (...) button
+    QuickAccess,
 }
 
 /// Element manipulation operation
@@ -230,11 +626,129 @@ pub struct ElementOp {
 pub enum ElementOpType {
     /// Click on element
     Click,
+    /// Double-click on element
+    DoubleClick,
+    /// Right-click on element
+    RightClick,
+    /// Click on element at an offset from its top left corner
+    ClickAt {
+        /// Horizontal offset, in CSS pixels
+        x: f64,
+        /// Vertical offset, in CSS pixels
+        y: f64,
+    },
+    /// Press and hold on element, for time in milliseconds
+    LongPress(u64),
+    /// Move mouse over element
+    Hover,
+    /// Drag element onto another element
+    DragTo(ElementSelector),
+    /// Drag element by an offset
+    DragBy {
+        /// Horizontal offset, in CSS pixels
+        x: f64,
+        /// Vertical offset, in CSS pixels
+        y: f64,
+    },
+    /// Swipe (touch drag) from element by an offset
+    Swipe {
+        /// Horizontal offset, in CSS pixels
+        x: f64,
+        /// Vertical offset, in CSS pixels
+        y: f64,
+    },
+    /// Tap (touch) on element
+    Tap,
     /// Wait for element to be created
     WaitFor,
+    /// Wait for element to be visible
+    WaitForVisible,
+    /// Wait for element to be hidden or removed
+    WaitForHidden,
+    /// Wait for element to be enabled
+    WaitForEnabled,
+    /// Wait for element text to equal a value
+    WaitForText(String),
+    /// Wait for element text to contain a value
+    WaitForTextContains(String),
     /// Focus the element
     Focus,
     /// Scroll the element into view
     ScrollTo,
+    /// Type text into the element
+    TypeText(String),
+    /// Clear the element's text
+    Clear,
+    /// Count elements matching the selector
+    Count,
+    /// Retrieve the element's text
+    GetText,
+    /// Retrieve the element's attribute value
+    GetAttribute(String),
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ordering_comparisons() {
+        assert!(Comparison::GreaterThan(0.into()).compare(Some(&42.into())));
+        assert!(Comparison::LessThanEquals(15.into()).compare(Some(&15.into())));
+        assert!(Comparison::GreaterThanEquals(3.into()).compare(Some(&" 3 ".into())));
+        assert!(!Comparison::LessThan("9".into()).compare(Some(&"10".into())));
+        assert!(Comparison::LessThan("b".into()).compare(Some(&"a".into())));
+        assert!(!Comparison::GreaterThan(0.into()).compare(Some(&"full".into())));
+        assert!(!Comparison::GreaterThan(0.into()).compare(Some(&serde_json::Value::Null)));
+        assert!(!Comparison::GreaterThan(0.into()).compare(None));
+    }
+
+    #[test]
+    fn structured_comparisons() {
+        let value = serde_json::json!({
+            "battery": { "percent": 87.5, "charging": false },
+            "plugins": [ { "name": "PowerTools" }, { "name": "CSS Loader" } ],
+        });
+        let pointer = Comparison::Pointer {
+            pointer: "/battery/percent".into(),
+            comparison: Box::new(Comparison::ApproxEquals { expected: 87.0, tolerance: 0.5 }),
+        };
+        assert!(pointer.compare(Some(&value)));
+        let missing = Comparison::Pointer { pointer: "/battery/voltage".into(), comparison: Box::new(Comparison::Exists) };
+        assert!(!missing.compare(Some(&value)));
+        let path = Comparison::JsonPath {
+            path: "$.plugins[*].name".into(),
+            comparison: Box::new(Comparison::Length(Box::new(Comparison::Equals(2.into())))),
+        };
+        assert!(path.compare(Some(&value)));
+        let one_of = Comparison::JsonPath {
+            path: "$.plugins[0].name".into(),
+            comparison: Box::new(Comparison::OneOf(vec!["PowerTools".into(), "Decky".into()])),
+        };
+        assert!(one_of.compare(Some(&value)));
+        let one_of = Comparison::OneOf(vec!["a".into(), 1.into()]);
+        assert_eq!(one_of.pseudocode_assert(Some(&"b".into())), r#""b" in ["a",1]"#);
+        assert_eq!(one_of.to_string(), r#"one of ["a",1]"#);
+        assert!(Comparison::TextMatches(r"^\d+W$".into()).compare(Some(&"15W".into())));
+        assert!(!Comparison::TextMatches(r"^\d+W$".into()).compare(Some(&"Error".into())));
+    }
+
+    #[test]
+    fn combined_comparisons() {
+        let cmp = Comparison::All(vec![
+            Comparison::TextContains("W".into()),
+            Comparison::Not(Box::new(Comparison::TextContains("Error".into()))),
+        ]);
+        assert!(cmp.compare(Some(&"15W".into())));
+        assert_eq!(cmp.pseudocode_failure(Some(&"15W".into())), None);
+        assert!(!cmp.compare(Some(&"Error: 15W".into())));
+        assert_eq!(
+            cmp.pseudocode_failure(Some(&"Error: 15W".into())).unwrap(),
+            "!(\"Error: 15W\" must contain \"Error\")"
+        );
+        let any = Comparison::Any(vec![Comparison::Equals(1.into()), Comparison::Equals(2.into())]);
+        assert!(any.compare(Some(&2.into())));
+        assert_eq!(any.pseudocode_failure(Some(&3.into())).unwrap(), "3 == 1 and 3 == 2");
+    }
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::SelectorLibrary;
+
+impl SelectorLibrary {
+    /// Load and combine selector library files, resolving them relative to `dir`
+    pub fn load<P: AsRef<Path>>(files: &[P], dir: Option<&Path>) -> std::io::Result<Self> {
+        let mut library = Self::default();
+        for file in files {
+            let path = dir.map(|dir| dir.join(file.as_ref())).unwrap_or_else(|| file.as_ref().to_owned());
+            let reader = std::io::BufReader::new(std::fs::File::open(&path)
+                .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?);
+            let other: Self = serde_json::from_reader(reader)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+            for name in other.tabs.keys().filter(|name| library.tabs.contains_key(*name))
+                .chain(other.elements.keys().filter(|name| library.elements.contains_key(*name))) {
+                log::warn!("Selector {} from {} replaces an earlier definition", name, path.display());
+            }
+            library.tabs.extend(other.tabs);
+            library.elements.extend(other.elements);
+        }
+        Ok(library)
+    }
+
+    /// Replace library references in test JSON with their definitions
+    pub fn resolve(&self, json: &mut Value) -> Result<(), String> {
+        self.resolve_at(json, &mut String::new(), &mut Vec::new(), false)
+    }
+
+    /// Replace library references when loading a test, leaving those named by a `${variable}` until it's known
+    pub fn resolve_loaded(&self, json: &mut Value) -> Result<(), String> {
+        self.resolve_at(json, &mut String::new(), &mut Vec::new(), true)
+    }
+
+    fn resolve_at(&self, json: &mut Value, path: &mut String, resolving: &mut Vec<String>, deferred: bool) -> Result<(), String> {
+        match json {
+            Value::Object(fields) if fields.get("by").and_then(Value::as_str) == Some("Library") => {
+                let (kind, name, definition) = if let Some(name) = fields.get("element").and_then(Value::as_str) {
+                    ("element", name.to_owned(), self.elements.get(name).map(serde_json::to_value))
+                } else if let Some(name) = fields.get("tab").and_then(Value::as_str) {
+                    ("tab", name.to_owned(), self.tabs.get(name).map(serde_json::to_value))
+                } else {
+                    return Err(format!("{}: library reference needs an `element` or `tab` name", display_path(path)));
+                };
+                if deferred && name.contains("${") {
+                    return Ok(());
+                }
+                let key = format!("{} {}", kind, name);
+                if resolving.contains(&key) {
+                    return Err(format!("{}: selector library {} `{}` refers to itself", display_path(path), kind, name));
+                }
+                let mut definition = definition
+                    .ok_or_else(|| format!("{}: unknown selector library {} `{}`", display_path(path), kind, name))?
+                    .map_err(|e| e.to_string())?;
+                resolving.push(key);
+                self.resolve_at(&mut definition, path, resolving, deferred)?;
+                resolving.pop();
+                *json = definition;
+            },
+            Value::Object(fields) => for (key, value) in fields.iter_mut() {
+                let len = path.len();
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+                self.resolve_at(value, path, resolving, deferred)?;
+                path.truncate(len);
+            },
+            Value::Array(items) => for (i, item) in items.iter_mut().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                self.resolve_at(item, path, resolving, deferred)?;
+                path.truncate(len);
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() { "(root)" } else { path }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolve_references() {
+        let library: SelectorLibrary = serde_json::from_value(serde_json::json!({
+            "tabs": { "QAM": { "by": "Title", "title": "QuickAccess" } },
+            "elements": {
+                "QAM panel": { "by": "CSS", "css": ".quickaccessmenu" },
+                "Plugin entry": { "by": "Within", "parent": { "by": "Library", "element": "QAM panel" }, "child": { "by": "Text", "text": "Plugin" } },
+                "Loop": { "by": "Parent", "selector": { "by": "Library", "element": "Loop" } }
+            }
+        })).expect("Unable to parse library");
+        let mut json = serde_json::json!({ "test": [ { "tab": { "by": "Library", "tab": "QAM" }, "operations": [
+            { "type": "Element", "element": { "by": "Library", "element": "Plugin entry" } }
+        ] } ] });
+        library.resolve(&mut json).expect("Unable to resolve references");
+        assert_eq!(json["test"][0]["tab"]["title"], "QuickAccess");
+        assert_eq!(json["test"][0]["operations"][0]["element"]["parent"]["css"], ".quickaccessmenu");
+
+        let mut json = serde_json::json!({ "test": [ { "operations": [ {}, { "element": { "by": "Library", "element": "Missing" } } ] } ] });
+        assert_eq!(library.resolve(&mut json).unwrap_err(), "test[0].operations[1].element: unknown selector library element `Missing`");
+        let mut json = serde_json::json!({ "element": { "by": "Library", "element": "Loop" } });
+        assert!(library.resolve(&mut json).unwrap_err().contains("refers to itself"));
+    }
+}
@@ -1,7 +1,13 @@
 //! JSON Runner implementation
+mod library;
+mod parameters;
 mod runner;
 mod structure;
+mod validate;
+mod variables;
 
 pub use runner::JsonRunner;
 pub use structure::Test;
 pub(super) use structure::*;
+pub use validate::ValidationError;
+pub(super) use variables::Variables;
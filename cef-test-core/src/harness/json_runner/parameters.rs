@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use super::Parameters;
+
+impl Parameters {
+    /// Load parameter rows, resolving files relative to `dir`
+    pub fn rows(&self, dir: Option<&Path>) -> std::io::Result<Vec<Map<String, Value>>> {
+        match self {
+            Self::Inline(rows) => Ok(rows.clone()),
+            Self::File { file } => {
+                let path = dir.map(|dir| dir.join(file)).unwrap_or_else(|| file.to_owned());
+                let content = std::fs::read_to_string(&path)?;
+                if path.extension().map(|ext| ext.eq_ignore_ascii_case("csv")).unwrap_or(false) {
+                    parse_csv(&content)
+                } else {
+                    Ok(serde_json::from_str(&content)?)
+                }
+            }
+        }
+    }
+}
+
+/// Parse CSV content, where the first record names the columns
+fn parse_csv(content: &str) -> std::io::Result<Vec<Map<String, Value>>> {
+    let mut records = csv_records(content).into_iter();
+    let header = records.next().unwrap_or_default();
+    records.enumerate()
+        .map(|(i, record)| {
+            if record.len() != header.len() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("CSV row {} has {} fields, expected {}", i + 1, record.len(), header.len()),
+                ));
+            }
+            Ok(header.iter().cloned().zip(record.into_iter().map(Value::String)).collect())
+        })
+        .collect()
+}
+
+/// Split CSV content into records of fields, supporting `"quoted, ""escaped"""` fields
+fn csv_records(content: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {},
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                if !(record.len() == 1 && record[0].is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+            },
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_csv_rows() {
+        let rows = parse_csv("setting,value\r\nvolume,10\n\"name, quoted\",\"say \"\"hi\"\"\"\n").expect("Unable to parse CSV");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["setting"], "volume");
+        assert_eq!(rows[0]["value"], "10");
+        assert_eq!(rows[1]["setting"], "name, quoted");
+        assert_eq!(rows[1]["value"], "say \"hi\"");
+        assert!(parse_csv("a,b\n1\n").is_err());
+    }
+}
@@ -1,104 +1,552 @@
 use std::path::{Path, PathBuf};
 
-use super::super::{Instruction, Feedback, TestRunner, TestMetadata};
-use super::{Test, FailureMode, TestStepType};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::super::{Instruction, Feedback, TestRunner, TestMetadata, TestError};
+use super::{Test, SelectorLibrary, describe_error, FailureMode, TestStepType, MacroStep, IfStep, LoopStep, ForEachStep, TestCondition, TestOperation, TabDescriptor, RetryPolicy, Variables};
+
+/// Part of the test being run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Setup,
+    Test,
+    Teardown,
+    Done,
+}
 
 /// Test runner for specific JSON data structures.
 pub struct JsonRunner {
-    test_data: Test,
+    pub(super) test_data: Test,
+    phase: Phase,
     step_i: usize,
     op_i: usize,
     success: bool,
-    filepath: Option<PathBuf>,
+    error: Option<TestError>,
+    pub(super) filepath: Option<PathBuf>,
+    /// Directories to search for macros which aren't next to the including file
+    macro_path: Vec<PathBuf>,
+    /// Canonical paths of the files being run, from the outermost test to this one
+    includes: Vec<PathBuf>,
+    /// Named selectors for references which variables introduce while running
+    library: SelectorLibrary,
+    /// Macro or control flow block being run
     current_macro: Option<Box<Self>>,
+    /// First failure in the current attempt of the current step
+    step_error: Option<TestError>,
+    instruction_attempt: u32,
+    step_attempt: u32,
+    test_attempt: u32,
+    retries: u32,
+    variables: Variables,
+    /// Variables given to the runner, which a rerun starts from
+    seeded: Variables,
+    /// Variable to store the next feedback value in
+    pending_save: Option<String>,
+    /// Next feedback decides a control flow condition
+    pending_condition: bool,
+    /// Pending control flow condition is an assertion
+    condition_assertion: bool,
+    /// Last decided control flow condition
+    condition: bool,
+    /// Loop iterations completed in the current step
+    iteration: usize,
 }
 
 impl JsonRunner {
-    /// Load test information from file
+    /// Load test information from file, resolving its selector library references
     pub fn from_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
         let file = std::io::BufReader::new(std::fs::File::open(path.as_ref())?);
-        let test = serde_json::from_reader(file)?;
-        Ok(Self {
-            test_data: test,
-            step_i: 0,
-            op_i: 0,
-            success: true,
-            filepath: Some(path.as_ref().into()),
-            current_macro: None,
-        })
+        let mut json: serde_json::Value = serde_json::from_reader(file)?;
+        let libraries: Vec<PathBuf> = match json.get("selectors") {
+            Some(libraries) => serde_json::from_value(libraries.clone())?,
+            None => Vec::new(),
+        };
+        let library = SelectorLibrary::load(&libraries, path.as_ref().parent())?;
+        library.resolve_loaded(&mut json)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let test = serde_path_to_error::deserialize(json)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, describe_error(e)))?;
+        let mut runner = Self::new(test);
+        runner.filepath = Some(path.as_ref().into());
+        runner.includes = vec![path.as_ref().canonicalize()?];
+        runner.library = library;
+        Ok(runner)
     }
 
     /// Construct JsonRunner in memory
     pub fn new(test: Test) -> Self {
         Self {
             test_data: test,
+            phase: Phase::Setup,
             step_i: 0,
             op_i: 0,
             success: true,
+            error: None,
             filepath: None,
+            macro_path: Vec::new(),
+            includes: Vec::new(),
+            library: SelectorLibrary::default(),
             current_macro: None,
+            step_error: None,
+            instruction_attempt: 0,
+            step_attempt: 0,
+            test_attempt: 0,
+            retries: 0,
+            variables: Variables::default(),
+            seeded: Variables::default(),
+            pending_save: None,
+            pending_condition: false,
+            condition_assertion: false,
+            condition: false,
+            iteration: 0,
+        }
+    }
+
+    /// Seed variables, for use as `${name}` in the test
+    pub fn with_variables<V: Into<serde_json::Value>>(mut self, variables: impl IntoIterator<Item = (String, V)>) -> Self {
+        for (name, value) in variables {
+            let value = value.into();
+            self.seeded.set(name.clone(), value.clone());
+            self.variables.set(name, value);
+        }
+        self
+    }
+
+    /// Search these directories for macros which aren't found next to the including file
+    pub fn with_macro_path(mut self, macro_path: Vec<PathBuf>) -> Self {
+        self.macro_path = macro_path;
+        self
+    }
+
+    /// Expand a parameterized test into one runner per parameter row.
+    /// Row values are available as variables, and each instance's id gets the row number as a suffix.
+    pub fn instances(self) -> std::io::Result<Vec<Self>> {
+        let parameters = if let Some(parameters) = &self.test_data.parameters {
+            parameters.rows(self.filepath.as_deref().and_then(Path::parent))?
+        } else {
+            return Ok(vec![self]);
+        };
+        Ok(parameters.into_iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let mut test = self.test_data.clone();
+                test.parameters = None;
+                test.info.id = format!("{}#{}", test.info.id, i + 1);
+                test.info.name = format!("{} [{}]", test.info.name, i + 1);
+                let mut instance = Self::new(test);
+                instance.filepath = self.filepath.clone();
+                instance.macro_path = self.macro_path.clone();
+                instance.includes = self.includes.clone();
+                instance.library = self.library.clone();
+                instance.variables = self.variables.clone();
+                instance.seeded = self.seeded.clone();
+                instance.with_variables(row)
+            })
+            .collect())
+    }
+
+    fn phase_steps(test: &Test, phase: Phase) -> &[TestStepType] {
+        match phase {
+            Phase::Setup => &test.setup,
+            Phase::Test => &test.test,
+            Phase::Teardown => &test.teardown,
+            Phase::Done => &[],
+        }
+    }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        match Self::phase_steps(&self.test_data, self.phase).get(self.step_i) {
+            Some(TestStepType::Regular(step)) => step.retry.as_ref().unwrap_or(&self.test_data.info.retry),
+            _ => &self.test_data.info.retry,
+        }
+    }
+
+    /// Run a block of steps (with this runner's settings and variables) until it's done
+    fn start_block(&mut self, steps: Vec<TestStepType>) -> Option<Instruction> {
+        let mut info = self.test_data.info.clone();
+        // the whole test is retried by the top-level runner
+        info.retry.test = 0;
+        let mut block = Self::new(Test {
+            info,
+            selectors: Vec::new(),
+            parameters: None,
+            setup: Vec::new(),
+            test: steps,
+            teardown: Vec::new(),
+        });
+        block.filepath = self.filepath.clone();
+        block.macro_path = self.macro_path.clone();
+        block.includes = self.includes.clone();
+        block.library = self.library.clone();
+        block.variables = self.variables.clone();
+        let instr = block.next(Feedback::Start);
+        if instr.is_some() {
+            self.current_macro = Some(Box::new(block));
+        } else {
+            self.variables = block.variables;
+        }
+        instr
+    }
+
+    /// Find a macro file, relative to this file's directory or else in the macro library
+    pub(super) fn resolve_macro(&self, file: &Path) -> PathBuf {
+        let local = match self.filepath.as_deref().and_then(Path::parent) {
+            Some(dir) => dir.join(file),
+            None => file.to_owned(),
+        };
+        if local.exists() {
+            return local;
+        }
+        self.macro_path.iter()
+            .map(|dir| dir.join(file))
+            .find(|path| path.exists())
+            .unwrap_or(local)
+    }
+
+    /// Load a macro and run it with the given arguments until it's done
+    fn start_macro(&mut self, name: &str, file: &Path, args: &serde_json::Map<String, serde_json::Value>) -> Result<Option<Instruction>, TestError> {
+        let path = self.resolve_macro(file);
+        log::debug!("Macro {} expanded to {}", name, path.display());
+        let canonical = path.canonicalize()
+            .map_err(|e| TestError::Other(format!("Macro {} ({}) failed to load: {}", name, path.display(), e)))?;
+        if self.includes.contains(&canonical) {
+            let chain: Vec<String> = self.includes.iter().chain(std::iter::once(&canonical)).map(|p| p.display().to_string()).collect();
+            return Err(TestError::Other(format!("Macro {} is included recursively: {}", name, chain.join(" -> "))));
+        }
+        let mut new_macro = Self::from_file(&path)
+            .map_err(|e| TestError::Other(format!("Macro {} ({}) failed to load: {}", name, path.display(), e)))?;
+        new_macro.macro_path = self.macro_path.clone();
+        new_macro.includes = self.includes.clone();
+        new_macro.includes.push(canonical);
+        new_macro.variables = self.variables.clone();
+        for (arg, value) in self.variables.interpolate(args) {
+            new_macro.variables.set(arg, value);
+        }
+        let instr = new_macro.next(Feedback::Start);
+        if instr.is_some() {
+            self.current_macro = Some(Box::new(new_macro));
+        } else {
+            log::warn!("Macro {} ({}) is empty!", name, file.display());
+        }
+        Ok(instr)
+    }
+
+    /// Turn an operation into an instruction, filling in variables and selector library references
+    fn instruction(&mut self, operation: &TestOperation, tab: &TabDescriptor) -> Result<Instruction, TestError> {
+        let operation = self.resolve_selectors(self.variables.interpolate(operation))?;
+        let tab = self.resolve_selectors(self.variables.interpolate(tab))?;
+        self.pending_save = operation.save.clone();
+        Ok(operation.into_instruction(tab, self.test_data.info.timeout))
+    }
+
+    /// Replace selector library references, including any which came from variables
+    fn resolve_selectors<T: Serialize + DeserializeOwned>(&self, item: T) -> Result<T, TestError> {
+        let mut json = serde_json::to_value(item).map_err(|e| TestError::Other(e.to_string()))?;
+        self.library.resolve(&mut json).map_err(TestError::Other)?;
+        serde_json::from_value(json).map_err(|e| TestError::Other(e.to_string()))
+    }
+
+    /// Handle an instruction which couldn't be built like one which failed
+    fn fail_instruction(&mut self, error: TestError) -> Option<Instruction> {
+        log::error!("Step {}: {}", self.step_i, error);
+        self.next(Feedback::Error(error))
+    }
+
+    /// Ask for the control flow condition to be decided by the next feedback
+    fn check_condition(&mut self, condition: &TestCondition) -> Option<Instruction> {
+        match self.instruction(&condition.operation, &condition.tab) {
+            Ok(instruction) => {
+                self.pending_condition = true;
+                self.condition_assertion = matches!(instruction, Instruction::Assertion(_));
+                Some(instruction)
+            },
+            Err(e) => {
+                // don't leave an earlier step's condition to decide this one
+                self.condition = false;
+                self.fail_instruction(e)
+            },
+        }
+    }
+
+    /// Run (the rest of) an `If`, `Loop` or `ForEach` step
+    fn next_control(&mut self, step: &TestStepType) -> Option<Instruction> {
+        match step {
+            TestStepType::If(IfStep { condition, then, otherwise }) => {
+                if self.op_i == 0 {
+                    self.op_i = 1;
+                    return self.check_condition(condition);
+                } else if self.op_i == 1 {
+                    // mark the block as started, so that returning here means it has finished
+                    self.op_i = 2;
+                    let block = if self.condition { then.clone() } else { otherwise.clone() };
+                    if let Some(instr) = self.start_block(block) {
+                        return Some(instr);
+                    }
+                }
+            },
+            TestStepType::Loop(LoopStep { repeat, until, max }) => {
+                loop {
+                    match self.op_i {
+                        0 => {
+                            self.iteration = 0;
+                            self.op_i = 1;
+                        },
+                        // start of an iteration
+                        1 => {
+                            if self.step_error.is_some() {
+                                break;
+                            }
+                            if self.iteration >= *max as usize {
+                                if until.is_some() {
+                                    log::warn!("Loop in step {} stopped after {} iterations without its condition holding", self.step_i, max);
+                                }
+                                break;
+                            }
+                            self.iteration += 1;
+                            self.op_i = 2;
+                            if let Some(instr) = self.start_block(repeat.clone()) {
+                                return Some(instr);
+                            }
+                        },
+                        // iteration finished
+                        2 => {
+                            if let (Some(until), None) = (until, &self.step_error) {
+                                self.op_i = 3;
+                                return self.check_condition(until);
+                            }
+                            self.op_i = 1;
+                        },
+                        // until condition decided
+                        _ => {
+                            if self.condition {
+                                break;
+                            }
+                            self.op_i = 1;
+                        },
+                    }
+                }
+            },
+            TestStepType::ForEach(ForEachStep { for_each, item, steps: block }) => {
+                if self.op_i == 0 {
+                    self.iteration = 0;
+                    self.op_i = 1;
+                }
+                let items = match self.variables.get(for_each) {
+                    Some(serde_json::Value::Array(items)) => Some(items.clone()),
+                    _ => None,
+                };
+                match items {
+                    Some(items) => while self.step_error.is_none() && self.iteration < items.len() {
+                        self.variables.set(item.clone(), items[self.iteration].clone());
+                        self.iteration += 1;
+                        if let Some(instr) = self.start_block(block.clone()) {
+                            return Some(instr);
+                        }
+                    },
+                    None => if self.step_error.is_none() {
+                        self.step_error = Some(TestError::Other(format!("Variable {} is not an array", for_each)));
+                    },
+                }
+            },
+            TestStepType::Regular(_) | TestStepType::Macro(_) => {},
+        }
+        None
+    }
+
+    /// Record a failure which won't be retried
+    fn fail(&mut self, error: TestError) {
+        self.success = false;
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+
+    /// Start the current step over, if it has attempts left
+    fn retry_step(&mut self) -> bool {
+        if self.step_attempt < self.retry_policy().step {
+            self.step_attempt += 1;
+            self.retries += 1;
+            log::warn!("Retrying step {} (attempt {}/{}) after failure: {}",
+                self.step_i, self.step_attempt + 1, self.retry_policy().step + 1,
+                self.step_error.as_ref().map(|e| e.to_string()).unwrap_or_default());
+            self.op_i = 0;
+            self.instruction_attempt = 0;
+            self.step_error = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Finish the current step and go to the next one
+    fn end_step(&mut self) {
+        if let Some(e) = self.step_error.take() {
+            self.fail(e);
+        }
+        self.op_i = 0;
+        self.step_i += 1;
+        self.step_attempt = 0;
+        self.instruction_attempt = 0;
+    }
+
+    /// Go to the start of the next part of the test
+    fn next_phase(&mut self) {
+        self.phase = match self.phase {
+            Phase::Setup => Phase::Test,
+            Phase::Test => Phase::Teardown,
+            Phase::Teardown | Phase::Done => Phase::Done,
+        };
+        self.step_i = 0;
+        self.op_i = 0;
+        self.step_attempt = 0;
+        self.instruction_attempt = 0;
+    }
+
+    /// Skip the rest of the setup and test steps, since teardown must always run
+    fn skip_to_teardown(&mut self) {
+        if matches!(self.phase, Phase::Setup | Phase::Test) {
+            self.phase = Phase::Test;
+            self.next_phase();
         }
     }
 }
 
 impl TestRunner for JsonRunner {
     fn next(&mut self, feedback: Feedback) -> Option<Instruction> {
-        self.success = feedback.is_ok();
-        let fail_mode = self.test_data.info.fail_mode.clone();
-        if matches!(fail_mode, FailureMode::FastFail) && !feedback.is_ok() {
-            return None;
+        let mut feedback = feedback;
+        if let Some(name) = self.pending_save.take() {
+            if let Feedback::Value(value) = &feedback {
+                log::debug!("Saving {} as variable {}", value, name);
+                self.variables.set(name, value.clone());
+            }
         }
         // handle in-progress macro execution
         if let Some(current_macro) = &mut self.current_macro {
             if let Some(instr) = current_macro.next(feedback.clone()) {
                 return Some(instr);
-            } else {
-                self.current_macro = None;
+            }
+            // the macro handles (and retries) its own failures, so only its outcome matters here
+            let outcome = current_macro.meta();
+            self.retries += outcome.retries;
+            let mut variables = std::mem::take(&mut current_macro.variables);
+            self.current_macro = None;
+            // macro arguments only exist within the macro
+            if let Some(TestStepType::Macro(MacroStep { args, .. })) = Self::phase_steps(&self.test_data, self.phase).get(self.step_i) {
+                for arg in args.keys() {
+                    match self.variables.get(arg) {
+                        Some(value) => variables.set(arg.clone(), value.clone()),
+                        None => { variables.remove(arg); },
+                    }
+                }
+            }
+            self.variables = variables;
+            feedback = match outcome.error {
+                Some(e) if !outcome.success => Feedback::Error(e),
+                _ => Feedback::Success,
+            };
+        } else if self.pending_condition {
+            self.pending_condition = false;
+            self.condition = TestCondition::holds(&feedback, self.condition_assertion);
+            log::debug!("Condition in step {} is {} ({:?})", self.step_i, self.condition, feedback);
+            feedback = Feedback::Success;
+        } else if let Some(e) = feedback.error() {
+            let policy = self.retry_policy().clone();
+            // only regular steps' operations can be run again on their own
+            let retry = match Self::phase_steps(&self.test_data, self.phase).get(self.step_i) {
+                Some(TestStepType::Regular(step)) if self.op_i != 0 && self.instruction_attempt < policy.instruction =>
+                    Some((step.operations[self.op_i - 1].clone(), step.tab.clone())),
+                _ => None,
+            };
+            if let Some((operation, tab)) = retry {
+                let backoff = std::time::Duration::from_millis(policy.backoff) * 2u32.saturating_pow(self.instruction_attempt);
+                self.instruction_attempt += 1;
+                self.retries += 1;
+                log::warn!("Retrying step {}, operation {} (attempt {}/{}) in {}ms after failure: {}",
+                    self.step_i, self.op_i - 1, self.instruction_attempt + 1, policy.instruction + 1, backoff.as_millis(), e);
+                std::thread::sleep(backoff);
+                return match self.instruction(&operation, &tab) {
+                    Ok(instruction) => Some(instruction),
+                    Err(e) => self.fail_instruction(e),
+                };
+            }
+        }
+        self.instruction_attempt = 0;
+        let fail_mode = if self.phase == Phase::Teardown {
+            // teardown should clean up as much as possible
+            FailureMode::SkipInstructions
+        } else {
+            self.test_data.info.fail_mode.clone()
+        };
+        if let Some(e) = feedback.error() {
+            if self.step_error.is_none() {
+                self.step_error = Some(e.clone());
+            }
+            if matches!(fail_mode, FailureMode::FastFail) && !self.retry_step() {
+                log::info!("{:?} Failing instruction ({}), ending test", fail_mode, e);
+                self.end_step();
+                self.skip_to_teardown();
             }
         }
         #[allow(clippy::never_loop)]
-        'step_loop: while self.step_i < self.test_data.test.len() {
-            let step = &self.test_data.test[self.step_i];
+        'step_loop: while self.phase != Phase::Done {
+            let steps = Self::phase_steps(&self.test_data, self.phase);
+            if self.step_i >= steps.len() {
+                self.next_phase();
+                continue 'step_loop;
+            }
+            let step = &steps[self.step_i];
             match step {
                 TestStepType::Regular(step) => {
                     'op_loop: while self.op_i < step.operations.len() {
-                        if matches!(fail_mode, FailureMode::SkipInstructions) && !feedback.is_ok() {
-                            log::info!("{:?} Failing instruction, going to next step", fail_mode);
-                            break 'op_loop;
+                        if matches!(fail_mode, FailureMode::SkipInstructions) {
+                            if let Some(e) = &self.step_error {
+                                log::info!("{:?} Failing instruction ({}), going to next step", fail_mode, e);
+                                break 'op_loop;
+                            }
                         }
-                        let instruction = &step.operations[self.op_i];
+                        let (operation, tab) = (step.operations[self.op_i].clone(), step.tab.clone());
                         log::debug!("Performing step {}, operation {}", self.step_i, self.op_i);
                         self.op_i += 1;
-                        return Some(instruction.clone().into_instruction(step.tab.clone()));
+                        return match self.instruction(&operation, &tab) {
+                            Ok(instruction) => Some(instruction),
+                            Err(e) => self.fail_instruction(e),
+                        };
                     }
                 },
-                TestStepType::Macro { name, file } => {
-                    let path = if let Some(filepath) = &self.filepath {
-                        filepath.join(file)
-                    } else {
-                        file.to_owned()
-                    };
-                    log::debug!("Macro {} expanded to {}", name, path.display());
-                    self.step_i += 1;
-                    match Self::from_file(&path) {
-                        Ok(mut new_macro) => {
-                            if let Some(instr) = new_macro.next(feedback.clone()) {
-                                self.current_macro = Some(Box::new(new_macro));
-                                return Some(instr);
-                            } else {
-                                log::warn!("Macro {} ({}) is empty!", name, file.display());
-                            }
-                        },
-                        Err(e) => log::error!("Macro {} ({}) failed to load: {}", name, file.display(), e),
+                TestStepType::Macro(MacroStep { name, file, args }) => {
+                    if self.op_i == 0 {
+                        // mark the macro as started, so that returning here means it has finished
+                        self.op_i = 1;
+                        let (name, file, args) = (name.clone(), file.clone(), args.clone());
+                        match self.start_macro(&name, &file, &args) {
+                            Ok(Some(instr)) => return Some(instr),
+                            Ok(None) => {},
+                            Err(e) => {
+                                log::error!("{}", e);
+                                self.step_error = Some(e);
+                            },
+                        }
                     }
-                }
+                },
+                control => {
+                    let control = control.clone();
+                    if let Some(instr) = self.next_control(&control) {
+                        return Some(instr);
+                    }
+                },
             }
-            if matches!(fail_mode, FailureMode::SkipSteps) && !self.success {
+            if self.step_error.is_some() && self.retry_step() {
+                continue 'step_loop;
+            }
+            let step_failed = self.step_error.is_some();
+            self.end_step();
+            if step_failed && self.phase == Phase::Setup {
+                log::info!("Setup failed, skipping test");
+                self.skip_to_teardown();
+            } else if step_failed && matches!(fail_mode, FailureMode::SkipSteps | FailureMode::FastFail) {
                 log::info!("{:?} Failing step complete, ending test", fail_mode);
-                break 'step_loop;
+                self.skip_to_teardown();
             }
-            self.op_i = 0;
-            self.step_i += 1;
         }
         None
     }
@@ -106,6 +554,328 @@ impl TestRunner for JsonRunner {
     fn meta(&self) -> TestMetadata {
         let mut metadata: TestMetadata = self.test_data.info.clone().into();
         metadata.success = self.success;
+        metadata.error = self.error.clone();
+        metadata.retries = self.retries;
         metadata
     }
+
+    fn retry(&mut self) -> bool {
+        if self.test_attempt < self.test_data.info.retry.test {
+            self.test_attempt += 1;
+            self.retries += 1;
+            log::warn!("Rerunning test {} (attempt {}/{})", self.test_data.info.id, self.test_attempt + 1, self.test_data.info.retry.test + 1);
+            self.phase = Phase::Setup;
+            self.step_i = 0;
+            self.op_i = 0;
+            self.success = true;
+            self.error = None;
+            self.current_macro = None;
+            self.step_error = None;
+            self.instruction_attempt = 0;
+            self.step_attempt = 0;
+            self.pending_condition = false;
+            self.iteration = 0;
+            self.variables = self.seeded.clone();
+            self.pending_save = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn recovers_failures(&self) -> bool {
+        // retries and control flow conditions expect some instructions to fail
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::super::{TestOp, GeneralOpType, TabOpType};
+    use serde_json::json;
+
+    /// Parse a test with the usual info (overridden by `info`) and the given steps and other fields
+    fn test_with(info: serde_json::Value, fields: serde_json::Value) -> Test {
+        let mut json = json!({
+            "info": { "name": "Runner test", "blame": "cef-test", "id": "runner", "output": "runner.json", "fail_mode": "FastFail" }
+        });
+        if let (Some(defaults), serde_json::Value::Object(info)) = (json["info"].as_object_mut(), info) {
+            defaults.extend(info);
+        }
+        if let (Some(json), serde_json::Value::Object(fields)) = (json.as_object_mut(), fields) {
+            json.extend(fields);
+        }
+        serde_json::from_value(json).expect("Unable to parse test")
+    }
+
+    /// Regular step in the SP tab
+    fn step(operations: serde_json::Value) -> serde_json::Value {
+        json!({ "tab": { "by": "Title", "title": "SP" }, "operations": operations })
+    }
+
+    fn test_data(retry: serde_json::Value) -> Test {
+        test_with(json!({ "retry": retry }), json!({
+            "test": [ step(json!([
+                { "type": "Sleep", "milliseconds": 1 },
+                { "type": "Sleep", "milliseconds": 2 }
+            ])) ]
+        }))
+    }
+
+    /// Control flow condition in the SP tab
+    fn condition(mut operation: serde_json::Value) -> serde_json::Value {
+        operation["tab"] = json!({ "by": "Title", "title": "SP" });
+        operation
+    }
+
+    /// Runner for the test with these variables seeded
+    fn runner_with(test: Test, variables: &[(&str, &str)]) -> JsonRunner {
+        JsonRunner::new(test).with_variables(variables.iter().map(|(name, value)| (name.to_string(), *value)))
+    }
+
+    fn sleep_of(instruction: Option<Instruction>) -> u64 {
+        match instruction {
+            Some(Instruction::Operation(TestOp { op: GeneralOpType::Tab(TabOpType::Sleep(ms)), .. })) => ms,
+            _ => panic!("Expected sleep instruction"),
+        }
+    }
+
+    fn eval_of(instruction: Option<Instruction>) -> String {
+        match instruction {
+            Some(Instruction::Operation(TestOp { op: GeneralOpType::Tab(TabOpType::Evaluate(code)), .. })) => code,
+            _ => panic!("Expected evaluate instruction"),
+        }
+    }
+
+    fn failure() -> Feedback {
+        Feedback::Error(TestError::Other("flaky".into()))
+    }
+
+    #[test]
+    fn retry_instruction_then_pass() {
+        let mut runner = JsonRunner::new(test_data(json!({ "instruction": 1 })));
+        assert!(runner.next(Feedback::Start).is_some());
+        assert!(runner.next(failure()).is_some()); // retry of operation 0
+        assert!(runner.next(Feedback::Success).is_some()); // operation 1
+        assert!(runner.next(Feedback::Success).is_none());
+        let meta = runner.meta();
+        assert!(meta.success);
+        assert_eq!(meta.retries, 1);
+    }
+
+    #[test]
+    fn retry_step_then_fail() {
+        let mut runner = JsonRunner::new(test_data(json!({ "step": 1 })));
+        assert!(runner.next(Feedback::Start).is_some());
+        assert!(runner.next(Feedback::Success).is_some());
+        assert!(runner.next(failure()).is_some()); // step restarts at operation 0
+        assert!(runner.next(Feedback::Success).is_some());
+        assert!(runner.next(failure()).is_none());
+        let meta = runner.meta();
+        assert!(!meta.success);
+        assert_eq!(meta.retries, 1);
+        assert!(!runner.retry());
+    }
+
+    #[test]
+    fn no_instruction_retry_in_control_flow() {
+        let mut runner = JsonRunner::new(test_with(json!({ "retry": { "instruction": 2 } }), json!({
+            "test": [
+                { "if": condition(json!({ "type": "Assert", "element": { "by": "Library", "element": "Missing" }, "assert": "Exists" })),
+                  "then": [] }
+            ]
+        })));
+        assert!(runner.next(Feedback::Start).is_none());
+        let meta = runner.meta();
+        assert!(!meta.success);
+        assert_eq!(meta.retries, 0);
+    }
+
+    #[test]
+    fn retry_test_from_seeded_variables() {
+        let test = test_with(json!({ "retry": { "test": 1 } }), json!({
+            "test": [ step(json!([
+                { "type": "Eval", "code": "load('${user}')", "save": "user" },
+                { "type": "Sleep", "milliseconds": 1 }
+            ])) ]
+        }));
+        let mut runner = runner_with(test, &[("user", "deck")]);
+        assert_eq!(eval_of(runner.next(Feedback::Start)), "load('deck')");
+        assert!(runner.next(Feedback::Value("other".into())).is_some());
+        assert!(runner.next(failure()).is_none());
+        assert!(runner.retry());
+        assert_eq!(eval_of(runner.next(Feedback::Start)), "load('deck')");
+    }
+
+    #[test]
+    fn teardown_after_fast_fail() {
+        let mut runner = JsonRunner::new(test_with(json!({}), json!({
+            "setup": [ step(json!([ { "type": "Sleep", "milliseconds": 1 } ])) ],
+            "test": [ step(json!([
+                { "type": "Sleep", "milliseconds": 2 },
+                { "type": "Sleep", "milliseconds": 3 }
+            ])) ],
+            "teardown": [ step(json!([ { "type": "Sleep", "milliseconds": 4 } ])) ]
+        })));
+        assert_eq!(sleep_of(runner.next(Feedback::Start)), 1);
+        assert_eq!(sleep_of(runner.next(Feedback::Success)), 2);
+        assert_eq!(sleep_of(runner.next(failure())), 4);
+        assert!(runner.next(Feedback::Success).is_none());
+        assert!(!runner.meta().success);
+    }
+
+    #[test]
+    fn expand_parameter_rows() {
+        let test = test_with(json!({}), json!({
+            "parameters": [ { "volume": 5 }, { "volume": 7 } ],
+            "test": [ step(json!([ { "type": "Eval", "code": "setVolume(${volume})" } ])) ]
+        }));
+        let instances = JsonRunner::new(test).instances().expect("Unable to expand parameters");
+        assert_eq!(instances.len(), 2);
+        for (mut runner, (id, script)) in instances.into_iter().zip([("runner#1", "setVolume(5)"), ("runner#2", "setVolume(7)")]) {
+            assert_eq!(runner.meta().id, id);
+            assert_eq!(eval_of(runner.next(Feedback::Start)), script);
+        }
+    }
+
+    #[test]
+    fn control_flow() {
+        let mut runner = JsonRunner::new(test_with(json!({}), json!({
+            "test": [
+                { "if": condition(json!({ "type": "Eval", "code": "popup" })),
+                  "then": [ step(json!([ { "type": "Sleep", "milliseconds": 1 } ])) ],
+                  "else": [ step(json!([ { "type": "Sleep", "milliseconds": 2 } ])) ] },
+                { "repeat": [ step(json!([ { "type": "Sleep", "milliseconds": 3 } ])) ],
+                  "until": condition(json!({ "type": "Eval", "code": "done", "save": "items" })),
+                  "max": 5 },
+                { "for_each": "items", "as": "item",
+                  "steps": [ step(json!([ { "type": "Eval", "code": "use(${item})" } ])) ] }
+            ]
+        })));
+        assert_eq!(eval_of(runner.next(Feedback::Start)), "popup");
+        // falsy condition runs the else branch
+        assert_eq!(sleep_of(runner.next(Feedback::Value(serde_json::Value::Null))), 2);
+        assert_eq!(sleep_of(runner.next(Feedback::Success)), 3);
+        assert_eq!(eval_of(runner.next(Feedback::Success)), "done");
+        assert_eq!(sleep_of(runner.next(failure())), 3);
+        assert_eq!(eval_of(runner.next(Feedback::Success)), "done");
+        assert_eq!(eval_of(runner.next(Feedback::Value(json!([4, 5])))), "use(4)");
+        assert_eq!(eval_of(runner.next(Feedback::Success)), "use(5)");
+        assert!(runner.next(Feedback::Success).is_none());
+        let meta = runner.meta();
+        assert!(meta.success);
+    }
+
+    #[test]
+    fn macro_library_and_recursion() {
+        let dir = std::env::temp_dir().join(format!("cef-test-macros-{}", std::process::id()));
+        let library = dir.join("library");
+        std::fs::create_dir_all(&library).expect("Unable to create test directory");
+        let info = r#""info": { "name": "Macro test", "blame": "cef-test", "id": "macro", "output": "macro.json", "fail_mode": "FastFail" }"#;
+        std::fs::write(library.join("open.json"), format!(r#"{{ {}, "test": [
+            {{ "tab": {{ "by": "Title", "title": "SP" }}, "operations": [ {{ "type": "Eval", "code": "open('${{plugin}}')" }} ] }}
+        ] }}"#, info)).expect("Unable to write macro");
+        std::fs::write(dir.join("main.json"), format!(r#"{{ {}, "test": [
+            {{ "name": "Open plugin", "file": "open.json", "args": {{ "plugin": "Foo" }} }},
+            {{ "name": "Itself", "file": "main.json" }}
+        ] }}"#, info)).expect("Unable to write test");
+        let mut runner = JsonRunner::from_file(dir.join("main.json")).expect("Unable to load test")
+            .with_macro_path(vec![library]);
+        match runner.next(Feedback::Start) {
+            Some(Instruction::Operation(TestOp { op: GeneralOpType::Tab(TabOpType::Evaluate(code)), .. })) => assert_eq!(code, "open('Foo')"),
+            _ => panic!("Expected evaluate instruction"),
+        }
+        assert!(runner.variables.get("plugin").is_none());
+        assert!(runner.next(Feedback::Success).is_none());
+        let meta = runner.meta();
+        std::fs::remove_dir_all(&dir).expect("Unable to remove test directory");
+        assert!(!meta.success);
+        assert!(meta.error.expect("Expected recursion error").to_string().contains("recursively"));
+    }
+
+    #[test]
+    fn passing_assertion_condition() {
+        let mut runner = JsonRunner::new(test_with(json!({}), json!({
+            "test": [
+                { "if": condition(json!({ "type": "Eval", "code": "count", "assert": { "Equals": 0 } })),
+                  "then": [ step(json!([ { "type": "Sleep", "milliseconds": 1 } ])) ],
+                  "else": [ step(json!([ { "type": "Sleep", "milliseconds": 2 } ])) ] }
+            ]
+        })));
+        assert!(matches!(runner.next(Feedback::Start), Some(Instruction::Assertion(_))));
+        // the harness reports the observed value of a passing assertion
+        assert_eq!(sleep_of(runner.next(Feedback::Value(0.into()))), 1);
+    }
+
+    #[test]
+    fn missing_element_condition() {
+        let mut runner = JsonRunner::new(test_with(json!({}), json!({
+            "test": [
+                { "if": condition(json!({ "type": "Assert", "element": { "by": "CSS", "css": ".popup" }, "assert": { "Attribute": { "attribute": "class", "comparison": { "Not": "Exists" } } } })),
+                  "then": [ step(json!([ { "type": "Sleep", "milliseconds": 1 } ])) ],
+                  "else": [ step(json!([ { "type": "Sleep", "milliseconds": 2 } ])) ] }
+            ]
+        })));
+        assert!(matches!(runner.next(Feedback::Start), Some(Instruction::Assertion(_))));
+        // the harness reports success when the element is missing as asserted
+        assert_eq!(sleep_of(runner.next(Feedback::Success)), 1);
+    }
+
+    #[test]
+    fn unbuildable_condition_after_true_condition() {
+        let branches = json!({
+            "then": [ step(json!([ { "type": "Sleep", "milliseconds": 1 } ])) ],
+            "else": [ step(json!([ { "type": "Sleep", "milliseconds": 2 } ])) ]
+        });
+        let mut first = branches.clone();
+        first["if"] = condition(json!({ "type": "Eval", "code": "ready" }));
+        let mut second = branches;
+        second["if"] = condition(json!({ "type": "Assert", "element": { "by": "Library", "element": "Missing" }, "assert": "Exists" }));
+        let mut runner = JsonRunner::new(test_with(json!({ "fail_mode": "SkipInstructions" }), json!({ "test": [ first, second ] })));
+        assert_eq!(eval_of(runner.next(Feedback::Start)), "ready");
+        assert_eq!(sleep_of(runner.next(Feedback::Value(true.into()))), 1);
+        // the second condition can't be built, so it doesn't hold
+        assert_eq!(sleep_of(runner.next(Feedback::Success)), 2);
+        assert!(runner.next(Feedback::Success).is_none());
+        assert!(!runner.meta().success);
+    }
+
+    #[test]
+    fn selector_library_reference_at_runtime() {
+        let test = || test_with(json!({}), json!({
+            "test": [ step(json!([
+                { "type": "Element", "element": { "by": "Library", "element": "${button}" }, "operation": "Click" }
+            ])) ]
+        }));
+        // unresolved reference fails the test instead of panicking
+        let mut runner = runner_with(test(), &[("button", "QAM button")]);
+        assert!(runner.next(Feedback::Start).is_none());
+        let meta = runner.meta();
+        assert!(!meta.success);
+        assert!(meta.error.expect("Expected reference error").to_string().contains("unknown selector library element `QAM button`"));
+        // references named by variables are resolved with the test's library
+        let mut runner = runner_with(test(), &[("button", "QAM button")]);
+        runner.library.elements.insert("QAM button".to_owned(), serde_json::from_value(json!({ "by": "CSS", "css": ".qam" })).expect("Unable to parse selector"));
+        assert!(matches!(runner.next(Feedback::Start), Some(Instruction::Operation(TestOp { op: GeneralOpType::Element(_), .. }))));
+    }
+
+    #[test]
+    fn save_and_interpolate_variable() {
+        let test = test_with(json!({}), json!({
+            "test": [ { "tab": { "by": "Title", "title": "${tab}" }, "operations": [
+                { "type": "Eval", "code": "1 + 1", "save": "sum" },
+                { "type": "Eval", "code": "check(${sum}, '${user}')" }
+            ] } ]
+        }));
+        let mut runner = runner_with(test, &[("tab", "SP"), ("user", "deck")]);
+        assert!(runner.next(Feedback::Start).is_some());
+        match runner.next(Feedback::Value(2.into())) {
+            Some(Instruction::Operation(TestOp { context, op: GeneralOpType::Tab(TabOpType::Evaluate(code)), .. })) => {
+                assert_eq!(context.to_string(), "Tab[title~=`SP`]");
+                assert_eq!(code, "check(2, 'deck')");
+            },
+            _ => panic!("Expected interpolated evaluate instruction"),
+        }
+    }
 }
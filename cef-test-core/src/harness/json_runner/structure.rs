@@ -1,28 +1,85 @@
 use std::convert::From;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use super::super::TestMetadata;
-use super::super::{TabSelector, ElementSelector, ElementOpType, ElementOp, TabOpType, GeneralOpType, GeneralAssertType, ElementAssert, ElementAssertionType, Instruction, TestAssert, TestOp, Comparison, /*TabAssert*/};
+use super::super::{TestMetadata, Feedback};
+use super::super::{TabSelector, ElementSelector, ElementOpType, ElementOp, TabOpType, GeneralOpType, GeneralAssertType, ElementAssert, ElementAssertionType, Instruction, TestAssert, Polling, TestOp, Comparison, TabAssert, KeyModifier, GamepadButton};
 
 /// Test descriptor
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct Test {
     pub(super) info: TestInfo,
+    /// Selector library files, relative to the test file
+    #[serde(default)]
+    pub(super) selectors: Vec<std::path::PathBuf>,
+    /// Inputs to run the test with, one test instance per row
+    #[serde(default)]
+    pub(super) parameters: Option<Parameters>,
+    /// Steps to run before the test
+    #[serde(default)]
+    pub(super) setup: Vec<TestStepType>,
     pub(super) test: Vec<TestStepType>,
+    /// Steps to run after the test, even if it failed
+    #[serde(default)]
+    pub(super) teardown: Vec<TestStepType>,
 }
 
 /// Test metadata
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct TestInfo {
     pub name: String,
     pub blame: String,
     pub id: String,
     pub output: String,
     pub fail_mode: FailureMode,
+    /// Default time limit for instructions, in milliseconds
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Default retry behaviour
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+/// Retry behaviour
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct RetryPolicy {
+    /// Extra attempts for a failing instruction
+    #[serde(default)]
+    pub instruction: u32,
+    /// Pause before retrying an instruction, in milliseconds (doubled for every further attempt)
+    #[serde(default)]
+    pub backoff: u64,
+    /// Extra attempts for a failing step
+    #[serde(default)]
+    pub step: u32,
+    /// Extra runs of a failing test (ignored for steps)
+    #[serde(default)]
+    pub test: u32,
+}
+
+/// Parameter table
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(untagged)]
+pub enum Parameters {
+    /// Rows of parameter values
+    Inline(Vec<serde_json::Map<String, serde_json::Value>>),
+    /// CSV (with a header row) or JSON file of rows, relative to the test file
+    File {
+        file: std::path::PathBuf,
+    },
+}
+
+/// Named selectors shared between tests, used with `{"by": "Library", "element": name}` or `{"by": "Library", "tab": name}`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct SelectorLibrary {
+    #[serde(default)]
+    pub tabs: std::collections::HashMap<String, TabDescriptor>,
+    #[serde(default)]
+    pub elements: std::collections::HashMap<String, ElementDescriptor>,
 }
 
 /// Failure behaviour
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub enum FailureMode {
     SkipInstructions,
     SkipSteps,
@@ -37,30 +94,188 @@ impl std::convert::From<TestInfo> for TestMetadata {
             output: Some(other.output.into()),
             author: Some(other.blame),
             success: true,
+            error: None,
+            retries: 0,
         }
     }
 }
 
 /// Test Step type
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 #[serde(untagged)]
 pub enum TestStepType {
     Regular(TestStep),
-    Macro {
-        name: String,
-        file: std::path::PathBuf,
+    Macro(MacroStep),
+    If(IfStep),
+    Loop(LoopStep),
+    ForEach(ForEachStep),
+}
+
+impl<'de> Deserialize<'de> for TestStepType {
+    // an untagged enum can't say why a step didn't match, so the variant is picked by its distinguishing field instead
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        if value.get("file").is_some() {
+            deserialize_tracked(value).map(Self::Macro)
+        } else if value.get("if").is_some() {
+            deserialize_tracked(value).map(Self::If)
+        } else if value.get("repeat").is_some() {
+            deserialize_tracked(value).map(Self::Loop)
+        } else if value.get("for_each").is_some() {
+            deserialize_tracked(value).map(Self::ForEach)
+        } else {
+            deserialize_tracked(value).map(Self::Regular)
+        }
+    }
+}
+
+/// Deserialize part of a test, keeping the location of errors within it
+fn deserialize_tracked<T: serde::de::DeserializeOwned, E: serde::de::Error>(value: serde_json::Value) -> Result<T, E> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let message = e.inner().to_string();
+        match e.path().to_string().as_str() {
+            "." => E::custom(message),
+            path => E::custom(format!(".{}", join_error_path(path, &message))),
+        }
+    })
+}
+
+/// Describe a deserialization error with where it happened, like `test[3].operations[2].element: unknown variant ...`
+pub(super) fn describe_error(error: serde_path_to_error::Error<serde_json::Error>) -> String {
+    let message = error.inner().to_string();
+    match error.path().to_string().as_str() {
+        "." => message.trim_start_matches('.').to_owned(),
+        path => join_error_path(path, &message),
+    }
+}
+
+fn join_error_path(path: &str, message: &str) -> String {
+    if message.starts_with('.') {
+        // location within a step
+        format!("{}{}", path, message)
+    } else {
+        format!("{}: {}", path, message)
+    }
+}
+
+/// Run the steps of another test file
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct MacroStep {
+    pub name: String,
+    /// Macro test file, relative to the including file's directory or a macro library directory
+    pub file: std::path::PathBuf,
+    /// Variables to set while running the macro
+    #[serde(default)]
+    pub args: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Run steps depending on a condition
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct IfStep {
+    #[serde(rename = "if")]
+    pub condition: TestCondition,
+    pub then: Vec<TestStepType>,
+    #[serde(default, rename = "else")]
+    pub otherwise: Vec<TestStepType>,
+}
+
+/// Run steps repeatedly
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct LoopStep {
+    pub repeat: Vec<TestStepType>,
+    /// Stop once this holds, checked after every iteration
+    #[serde(default)]
+    pub until: Option<TestCondition>,
+    /// Maximum number of iterations
+    pub max: u32,
+}
+
+/// Run steps for every item of an array variable
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct ForEachStep {
+    pub for_each: String,
+    /// Variable name for the current item
+    #[serde(rename = "as")]
+    pub item: String,
+    pub steps: Vec<TestStepType>,
+}
+
+/// Control flow condition.
+/// It holds when an assertion passes, or an operation succeeds with a truthy value; failures and falsy values don't fail the test.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct TestCondition {
+    pub tab: TabDescriptor,
+    #[serde(flatten)]
+    pub operation: TestOperation,
+}
+
+impl TestCondition {
+    /// Decide the condition from the feedback of its instruction.
+    /// Assertions hold when they pass, whatever value they observed.
+    pub fn holds(feedback: &Feedback, assertion: bool) -> bool {
+        if assertion {
+            return !feedback.is_fail();
+        }
+        match feedback {
+            Feedback::Success => true,
+            Feedback::Value(value) => crate::util::is_truthy(value),
+            _ => false,
+        }
     }
 }
 
 /// Regular test step
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct TestStep {
     pub tab: TabDescriptor,
-    pub operations: Vec<TestInstruction>,
+    pub operations: Vec<TestOperation>,
+    /// Retry behaviour, if different from the test's
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+}
+
+/// Test instruction with per-instruction options
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+pub struct TestOperation {
+    #[serde(flatten)]
+    pub instruction: TestInstruction,
+    /// Time limit, in milliseconds
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Variable to save the instruction's value in
+    #[serde(default)]
+    pub save: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for TestOperation {
+    // flattened and internally tagged enums lose track of where errors are, so element instructions are deserialized separately
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let mut value = serde_json::Value::deserialize(deserializer)?;
+        let fields = value.as_object_mut().ok_or_else(|| D::Error::custom("expected an operation object"))?;
+        let timeout = fields.remove("timeout").map(deserialize_tracked).transpose()
+            .map_err(|e: D::Error| D::Error::custom(join_error_path(".timeout", &e.to_string())))?.flatten();
+        let save = fields.remove("save").map(deserialize_tracked).transpose()
+            .map_err(|e: D::Error| D::Error::custom(join_error_path(".save", &e.to_string())))?.flatten();
+        let instruction = if fields.get("type").and_then(serde_json::Value::as_str) == Some("Element") {
+            fields.remove("type");
+            deserialize_tracked(value).map(TestInstruction::Element)?
+        } else {
+            deserialize_tracked(value)?
+        };
+        Ok(Self { instruction, timeout, save })
+    }
+}
+
+impl TestOperation {
+    pub fn into_instruction(self, tab: TabDescriptor, default_timeout: Option<u64>) -> Instruction {
+        let timeout = self.timeout.or(default_timeout).map(std::time::Duration::from_millis);
+        self.instruction.into_instruction(tab, timeout)
+    }
 }
 
 /// Tab metadata
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(tag = "by")]
 pub enum TabDescriptor {
     /// Select by tab title
@@ -69,6 +284,10 @@ pub enum TabDescriptor {
     Url{url: String},
     /// Select by tab identifier
     Id{id: String},
+    /// Select an iframe within a tab (same-origin only)
+    Frame{tab: Box<TabDescriptor>, frame: ElementDescriptor},
+    /// Use a named tab from a selector library (replaced when the test is loaded)
+    Library{tab: String},
 }
 
 impl From<TabDescriptor> for TabSelector {
@@ -77,12 +296,14 @@ impl From<TabDescriptor> for TabSelector {
             TabDescriptor::Title{title: t} => Self::TitleRegex(t),
             TabDescriptor::Url{url: u} => Self::UrlRegex(u),
             TabDescriptor::Id{id: i} => Self::Id(i),
+            TabDescriptor::Frame{tab, frame} => Self::Frame{tab: Box::new((*tab).into()), frame: frame.into()},
+            TabDescriptor::Library{tab} => unreachable!("Selector library tab {} must be resolved by the runner", tab),
         }
     }
 }
 
 /// Test instruction
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(tag = "type")]
 pub enum TestInstruction {
     /// Operate on an element
@@ -92,44 +313,136 @@ pub enum TestInstruction {
         /// Duration of pause
         milliseconds: u64,
     },
+    /// Execute Javascript in the tab
     Eval {
         /// Javascript to execute
         code: String,
-        /*
         /// Result assertion
-        assert: Option<Comparison>,
-        */
+        assert: Option<ValueComparison>,
+        /// Retry the assertion until it passes
+        #[serde(default)]
+        eventually: Option<Eventually>,
     },
     /// Assertion on an element
     Assert(TestElementAssertion),
+    /// Press a key in the tab
+    KeyPress {
+        /// Key name (e.g. `Enter`, `a`, `ArrowDown`)
+        key: String,
+        /// Modifier keys to hold down
+        #[serde(default)]
+        modifiers: Vec<KeyboardModifier>,
+    },
+    /// Press keys one after another in the tab
+    KeySequence {
+        /// Key names
+        keys: Vec<String>,
+    },
+    /// Press a controller button in the tab
+    Gamepad {
+        /// Button to press
+        button: ControllerButton,
+    },
+    /// Wait for Javascript expression to be truthy in the tab
+    WaitForScript {
+        /// Javascript to evaluate
+        code: String,
+    },
+    /// Wait for the tab to make no network requests for a period.
+    /// Requests already in flight when the wait starts aren't counted, so they may still be pending once it's idle.
+    WaitForNetworkIdle {
+        /// Quiet period, in milliseconds
+        #[serde(default = "TestInstruction::default_network_idle")]
+        idle: u64,
+    },
+    /// Wait for the tab's page to finish loading
+    WaitForLoad,
+    /// Wait for the tab to exist
+    WaitForTab,
 }
 
 impl TestInstruction {
-    pub fn into_instruction(self, tab: TabDescriptor) -> Instruction {
+    fn default_network_idle() -> u64 {
+        500
+    }
+
+    pub fn into_instruction(self, tab: TabDescriptor, timeout: Option<std::time::Duration>) -> Instruction {
         let selector: TabSelector = tab.into();
         match self {
             TestInstruction::Element(elem) => Instruction::Operation(TestOp {
                 context: selector,
+                timeout,
                 op: GeneralOpType::Element(elem.into()),
             }),
             TestInstruction::Sleep { milliseconds } => Instruction::Operation(TestOp {
                 context: selector,
+                timeout,
                 op: GeneralOpType::Tab(TabOpType::Sleep(milliseconds)),
             }),
-            TestInstruction::Eval { code } => Instruction::Operation(TestOp {
+            TestInstruction::Eval { code, assert: None, .. } => Instruction::Operation(TestOp {
                 context: selector,
+                timeout,
                 op: GeneralOpType::Tab(TabOpType::Evaluate(code)),
             }),
+            TestInstruction::Eval { code, assert: Some(comparison), eventually } => Instruction::Assertion(TestAssert {
+                context: selector,
+                timeout,
+                eventually: eventually.map(|e| e.into()),
+                assertion: GeneralAssertType::Tab(TabAssert::Evaluate {
+                    script: code,
+                    comparison: comparison.into(),
+                }),
+            }),
             TestInstruction::Assert(assertion) => Instruction::Assertion(TestAssert {
                 context: selector,
+                timeout,
+                eventually: assertion.eventually.clone().map(|e| e.into()),
                 assertion: GeneralAssertType::Element(assertion.into()),
             }),
+            TestInstruction::KeyPress { key, modifiers } => Instruction::Operation(TestOp {
+                context: selector,
+                timeout,
+                op: GeneralOpType::Tab(TabOpType::KeyPress {
+                    key,
+                    modifiers: modifiers.into_iter().map(|m| m.into()).collect(),
+                }),
+            }),
+            TestInstruction::KeySequence { keys } => Instruction::Operation(TestOp {
+                context: selector,
+                timeout,
+                op: GeneralOpType::Tab(TabOpType::KeySequence(keys)),
+            }),
+            TestInstruction::Gamepad { button } => Instruction::Operation(TestOp {
+                context: selector,
+                timeout,
+                op: GeneralOpType::Tab(TabOpType::GamepadButton(button.into())),
+            }),
+            TestInstruction::WaitForScript { code } => Instruction::Operation(TestOp {
+                context: selector,
+                timeout,
+                op: GeneralOpType::Tab(TabOpType::WaitForScript(code)),
+            }),
+            TestInstruction::WaitForNetworkIdle { idle } => Instruction::Operation(TestOp {
+                context: selector,
+                timeout,
+                op: GeneralOpType::Tab(TabOpType::WaitForNetworkIdle(idle)),
+            }),
+            TestInstruction::WaitForLoad => Instruction::Operation(TestOp {
+                context: selector,
+                timeout,
+                op: GeneralOpType::Tab(TabOpType::WaitForLoad),
+            }),
+            TestInstruction::WaitForTab => Instruction::Operation(TestOp {
+                context: selector,
+                timeout,
+                op: GeneralOpType::Tab(TabOpType::WaitForTab),
+            }),
         }
     }
 }
 
 /// Test element instruction
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct TestElementInstruction {
     pub element: ElementDescriptor,
     pub operation: ElementInteraction,
@@ -145,10 +458,39 @@ impl From<TestElementInstruction> for ElementOp {
 }
 
 /// Test element instruction
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct TestElementAssertion {
     pub element: ElementDescriptor,
     pub assert: ElementAssertion,
+    /// Retry the assertion until it passes
+    #[serde(default)]
+    pub eventually: Option<Eventually>,
+}
+
+/// Assertion retry behaviour
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct Eventually {
+    /// Time allowed for the assertion to pass, in milliseconds (default: instruction timeout)
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Pause between attempts, in milliseconds
+    #[serde(default = "Eventually::default_interval")]
+    pub interval: u64,
+}
+
+impl Eventually {
+    fn default_interval() -> u64 {
+        100
+    }
+}
+
+impl From<Eventually> for Polling {
+    fn from(value: Eventually) -> Self {
+        Self {
+            timeout: value.timeout.map(std::time::Duration::from_millis),
+            interval: std::time::Duration::from_millis(value.interval),
+        }
+    }
 }
 
 impl From<TestElementAssertion> for ElementAssert {
@@ -161,48 +503,268 @@ impl From<TestElementAssertion> for ElementAssert {
 }
 
 /// Element descriptor
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(tag = "by")]
 #[allow(clippy::upper_case_acronyms)]
 pub enum ElementDescriptor {
     /// Use CSS selector syntax
     CSS{css: String},
+    /// Use XPath syntax
+    XPath{xpath: String},
+    /// Select by exact visible text
+    Text{text: String},
+    /// Select by partial visible text
+    TextContains{text: String},
+    /// Select by ARIA role and accessible name
+    Role{role: String, name: Option<String>},
+    /// Select by attribute value (e.g. `data-testid`)
+    Attribute{attribute: String, value: String},
+    /// Select by placeholder text
+    Placeholder{placeholder: String},
+    /// Select form control by label text
+    Label{label: String},
+    /// Select first DOM node rendered by a React component
+    React{
+        component: String,
+        #[serde(default)]
+        props: serde_json::Map<String, serde_json::Value>,
+    },
+    /// Select the nth (zero-indexed) match of another selector
+    Nth{selector: Box<ElementDescriptor>, index: usize},
+    /// Select the last match of another selector
+    Last{selector: Box<ElementDescriptor>},
+    /// Select matches of child found inside matches of parent
+    Within{parent: Box<ElementDescriptor>, child: Box<ElementDescriptor>},
+    /// Select the parent element of matches of another selector
+    Parent{selector: Box<ElementDescriptor>},
+    /// Select siblings of matches of another selector
+    Sibling{of: Box<ElementDescriptor>, sibling: Box<ElementDescriptor>},
+    /// Select matches of another selector which contain text
+    WithText{selector: Box<ElementDescriptor>, text: String},
+    /// Select matches of child inside the document of an iframe (same-origin only)
+    Frame{frame: Box<ElementDescriptor>, child: Box<ElementDescriptor>},
+    /// Select matches of child inside the shadow root of a host element
+    Shadow{host: Box<ElementDescriptor>, child: Box<ElementDescriptor>},
+    /// Use CSS selector syntax, searching inside shadow roots and iframes
+    DeepCSS{css: String},
+    /// Use a named element from a selector library (replaced when the test is loaded)
+    Library{element: String},
 }
 
 impl From<ElementDescriptor> for ElementSelector {
     fn from(value: ElementDescriptor) -> Self {
         match value {
             ElementDescriptor::CSS{css: s} => Self::CSS(s),
+            ElementDescriptor::XPath{xpath: x} => Self::XPath(x),
+            ElementDescriptor::Text{text: t} => Self::Text(t),
+            ElementDescriptor::TextContains{text: t} => Self::TextContains(t),
+            ElementDescriptor::Role{role, name} => Self::Role{role, name},
+            ElementDescriptor::Attribute{attribute, value} => Self::Attribute{attribute, value},
+            ElementDescriptor::Placeholder{placeholder: p} => Self::Placeholder(p),
+            ElementDescriptor::Label{label: l} => Self::Label(l),
+            ElementDescriptor::React{component, props} => Self::React{component, props},
+            ElementDescriptor::Nth{selector, index} => Self::Nth{selector: Box::new((*selector).into()), index},
+            ElementDescriptor::Last{selector} => Self::Last(Box::new((*selector).into())),
+            ElementDescriptor::Within{parent, child} => Self::Within{parent: Box::new((*parent).into()), child: Box::new((*child).into())},
+            ElementDescriptor::Parent{selector} => Self::Parent(Box::new((*selector).into())),
+            ElementDescriptor::Sibling{of, sibling} => Self::Sibling{of: Box::new((*of).into()), sibling: Box::new((*sibling).into())},
+            ElementDescriptor::WithText{selector, text} => Self::WithText{selector: Box::new((*selector).into()), text},
+            ElementDescriptor::Frame{frame, child} => Self::Frame{frame: Box::new((*frame).into()), child: Box::new((*child).into())},
+            ElementDescriptor::Shadow{host, child} => Self::Shadow{host: Box::new((*host).into()), child: Box::new((*child).into())},
+            ElementDescriptor::DeepCSS{css} => Self::DeepCSS(css),
+            ElementDescriptor::Library{element} => unreachable!("Selector library element {} must be resolved by the runner", element),
         }
     }
 }
 
 /// Element operation
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub enum ElementInteraction {
     /// Click on element
     Click,
+    /// Double-click on element
+    DoubleClick,
+    /// Right-click on element
+    RightClick,
+    /// Click on element at an offset from its top left corner
+    ClickAt {
+        /// Horizontal offset, in CSS pixels
+        x: f64,
+        /// Vertical offset, in CSS pixels
+        y: f64,
+    },
+    /// Press and hold on element
+    LongPress {
+        /// Duration of press
+        milliseconds: u64,
+    },
+    /// Move mouse over element
+    Hover,
+    /// Drag element onto another element
+    DragTo {
+        /// Element to drop onto
+        target: ElementDescriptor,
+    },
+    /// Drag element by an offset
+    DragBy {
+        /// Horizontal offset, in CSS pixels
+        x: f64,
+        /// Vertical offset, in CSS pixels
+        y: f64,
+    },
+    /// Swipe (touch drag) from element by an offset
+    Swipe {
+        /// Horizontal offset, in CSS pixels
+        x: f64,
+        /// Vertical offset, in CSS pixels
+        y: f64,
+    },
+    /// Tap (touch) on element
+    Tap,
     /// Wait for element to be created
     WaitFor,
+    /// Wait for element to be visible
+    WaitForVisible,
+    /// Wait for element to be hidden or removed
+    WaitForHidden,
+    /// Wait for element to be enabled
+    WaitForEnabled,
+    /// Wait for element text to equal a value
+    WaitForText {
+        /// Expected text
+        text: String,
+    },
+    /// Wait for element text to contain a value
+    WaitForTextContains {
+        /// Expected text
+        text: String,
+    },
     /// Focus the element
     Focus,
     /// Scroll the element into view
     ScrollTo,
+    /// Type text into the element
+    TypeText {
+        /// Text to type
+        text: String,
+    },
+    /// Clear the element's text
+    Clear,
+    /// Count elements matching the selector
+    Count,
+    /// Retrieve the element's text
+    GetText,
+    /// Retrieve the element's attribute value
+    GetAttribute {
+        /// Attribute name
+        attribute: String,
+    },
 }
 
 impl From<ElementInteraction> for ElementOpType {
     fn from(value: ElementInteraction) -> Self {
         match value {
             ElementInteraction::Click => Self::Click,
+            ElementInteraction::DoubleClick => Self::DoubleClick,
+            ElementInteraction::RightClick => Self::RightClick,
+            ElementInteraction::ClickAt { x, y } => Self::ClickAt { x, y },
+            ElementInteraction::LongPress { milliseconds } => Self::LongPress(milliseconds),
+            ElementInteraction::Hover => Self::Hover,
+            ElementInteraction::DragTo { target } => Self::DragTo(target.into()),
+            ElementInteraction::DragBy { x, y } => Self::DragBy { x, y },
+            ElementInteraction::Swipe { x, y } => Self::Swipe { x, y },
+            ElementInteraction::Tap => Self::Tap,
             ElementInteraction::WaitFor => Self::WaitFor,
+            ElementInteraction::WaitForVisible => Self::WaitForVisible,
+            ElementInteraction::WaitForHidden => Self::WaitForHidden,
+            ElementInteraction::WaitForEnabled => Self::WaitForEnabled,
+            ElementInteraction::WaitForText { text } => Self::WaitForText(text),
+            ElementInteraction::WaitForTextContains { text } => Self::WaitForTextContains(text),
             ElementInteraction::Focus => Self::Focus,
             ElementInteraction::ScrollTo => Self::ScrollTo,
+            ElementInteraction::TypeText { text } => Self::TypeText(text),
+            ElementInteraction::Clear => Self::Clear,
+            ElementInteraction::Count => Self::Count,
+            ElementInteraction::GetText => Self::GetText,
+            ElementInteraction::GetAttribute { attribute } => Self::GetAttribute(attribute),
+        }
+    }
+}
+
+/// Keyboard modifier key
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, JsonSchema)]
+pub enum KeyboardModifier {
+    /// Alt key
+    Alt,
+    /// Control key
+    Ctrl,
+    /// Meta (Windows/Command) key
+    Meta,
+    /// Shift key
+    Shift,
+}
+
+impl From<KeyboardModifier> for KeyModifier {
+    fn from(value: KeyboardModifier) -> Self {
+        match value {
+            KeyboardModifier::Alt => Self::Alt,
+            KeyboardModifier::Ctrl => Self::Ctrl,
+            KeyboardModifier::Meta => Self::Meta,
+            KeyboardModifier::Shift => Self::Shift,
+        }
+    }
+}
+
+/// Controller button
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, JsonSchema)]
+pub enum ControllerButton {
+    A,
+    B,
+    X,
+    Y,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    Steam,
+    QuickAccess,
+}
+
+impl From<ControllerButton> for GamepadButton {
+    fn from(value: ControllerButton) -> Self {
+        match value {
+            ControllerButton::A => Self::A,
+            ControllerButton::B => Self::B,
+            ControllerButton::X => Self::X,
+            ControllerButton::Y => Self::Y,
+            ControllerButton::DPadUp => Self::DPadUp,
+            ControllerButton::DPadDown => Self::DPadDown,
+            ControllerButton::DPadLeft => Self::DPadLeft,
+            ControllerButton::DPadRight => Self::DPadRight,
+            ControllerButton::LeftBumper => Self::LeftBumper,
+            ControllerButton::RightBumper => Self::RightBumper,
+            ControllerButton::LeftTrigger => Self::LeftTrigger,
+            ControllerButton::RightTrigger => Self::RightTrigger,
+            ControllerButton::Select => Self::Select,
+            ControllerButton::Start => Self::Start,
+            ControllerButton::LeftStick => Self::LeftStick,
+            ControllerButton::RightStick => Self::RightStick,
+            ControllerButton::Steam => Self::Steam,
+            ControllerButton::QuickAccess => Self::QuickAccess,
         }
     }
 }
 
 /// Element operation
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub enum ElementAssertion {
     /// Assert element exists
     Exists,
@@ -210,6 +772,15 @@ pub enum ElementAssertion {
     TextEquals(String),
     /// Assert element contains text
     TextContains(String),
+    /// Assert on element attribute value
+    Attribute {
+        /// Attribute name
+        attribute: String,
+        /// Attribute value comparison
+        comparison: ValueComparison,
+    },
+    /// Assert on number of elements matching the selector
+    Count(ValueComparison),
 }
 
 impl From<ElementAssertion> for ElementAssertionType {
@@ -218,6 +789,190 @@ impl From<ElementAssertion> for ElementAssertionType {
             ElementAssertion::Exists => Self::Value(Comparison::Exists),
             ElementAssertion::TextEquals(t) => Self::Value(Comparison::TextEquals(t)),
             ElementAssertion::TextContains(t) => Self::Value(Comparison::TextContains(t)),
+            ElementAssertion::Attribute { attribute, comparison } => Self::Attribute { attribute, comparison: comparison.into() },
+            ElementAssertion::Count(comparison) => Self::Count(comparison.into()),
+        }
+    }
+}
+
+/// Value comparison
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub enum ValueComparison {
+    /// Assert value is non-null
+    Exists,
+    /// Assert value is non-null and not empty
+    ExistsNotEmpty,
+    /// Assert value equals text
+    TextEquals(String),
+    /// Assert value contains text
+    TextContains(String),
+    /// Assert value matches regex pattern
+    TextMatches(String),
+    /// Assert value == expected
+    Equals(serde_json::Value),
+    /// Assert value != expected
+    NotEquals(serde_json::Value),
+    /// Assert value < expected
+    LessThan(serde_json::Value),
+    /// Assert value <= expected
+    LessThanEquals(serde_json::Value),
+    /// Assert value > expected
+    GreaterThan(serde_json::Value),
+    /// Assert value >= expected
+    GreaterThanEquals(serde_json::Value),
+    /// Assert number is within tolerance of expected
+    ApproxEquals {
+        /// Expected number
+        expected: f64,
+        /// Maximum allowed difference
+        tolerance: f64,
+    },
+    /// Assert value equals one of expected
+    OneOf(Vec<serde_json::Value>),
+    /// Assert on array or string length
+    Length(Box<ValueComparison>),
+    /// Assert on value selected by JSON Pointer
+    Pointer {
+        /// JSON Pointer (e.g. `/battery/percent`)
+        pointer: String,
+        /// Selected value comparison
+        comparison: Box<ValueComparison>,
+    },
+    /// Assert on value selected by JSONPath
+    JsonPath {
+        /// JSONPath query (e.g. `$.battery.percent`)
+        path: String,
+        /// Selected value comparison
+        comparison: Box<ValueComparison>,
+    },
+    /// Assert all comparisons are satisfied
+    All(Vec<ValueComparison>),
+    /// Assert any comparison is satisfied
+    Any(Vec<ValueComparison>),
+    /// Assert comparison is not satisfied
+    Not(Box<ValueComparison>),
+}
+
+impl From<ValueComparison> for Comparison {
+    fn from(value: ValueComparison) -> Self {
+        match value {
+            ValueComparison::Exists => Self::Exists,
+            ValueComparison::ExistsNotEmpty => Self::ExistsNotEmpty,
+            ValueComparison::TextEquals(t) => Self::TextEquals(t),
+            ValueComparison::TextContains(t) => Self::TextContains(t),
+            ValueComparison::TextMatches(p) => Self::TextMatches(p),
+            ValueComparison::Equals(v) => Self::Equals(v),
+            ValueComparison::NotEquals(v) => Self::NotEquals(v),
+            ValueComparison::LessThan(v) => Self::LessThan(v),
+            ValueComparison::LessThanEquals(v) => Self::LessThanEquals(v),
+            ValueComparison::GreaterThan(v) => Self::GreaterThan(v),
+            ValueComparison::GreaterThanEquals(v) => Self::GreaterThanEquals(v),
+            ValueComparison::ApproxEquals { expected, tolerance } => Self::ApproxEquals { expected, tolerance },
+            ValueComparison::OneOf(v) => Self::OneOf(v),
+            ValueComparison::Length(c) => Self::Length(Box::new((*c).into())),
+            ValueComparison::Pointer { pointer, comparison } => Self::Pointer { pointer, comparison: Box::new((*comparison).into()) },
+            ValueComparison::JsonPath { path, comparison } => Self::JsonPath { path, comparison: Box::new((*comparison).into()) },
+            ValueComparison::All(c) => Self::All(c.into_iter().map(|c| c.into()).collect()),
+            ValueComparison::Any(c) => Self::Any(c.into_iter().map(|c| c.into()).collect()),
+            ValueComparison::Not(c) => Self::Not(Box::new((*c).into())),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_attribute_assertion() {
+        let assertion: TestInstruction = serde_json::from_str(r#"{
+            "type": "Assert",
+            "element": { "by": "CSS", "css": "div.tab" },
+            "assert": { "Attribute": { "attribute": "aria-selected", "comparison": { "Equals": "true" } } }
+        }"#).expect("Unable to parse attribute assertion");
+        let instruction = assertion.into_instruction(TabDescriptor::Title { title: "SP".into() }, None);
+        if let Instruction::Assertion(TestAssert { assertion: GeneralAssertType::Element(elem), .. }) = instruction {
+            if let ElementAssertionType::Attribute { attribute, comparison } = elem.assert {
+                assert_eq!(attribute, "aria-selected");
+                assert!(comparison.compare(Some(&"true".into())));
+                assert!(!comparison.compare(Some(&serde_json::Value::Null)));
+                return;
+            }
+        }
+        panic!("Attribute assertion parsed into wrong instruction");
+    }
+
+    #[test]
+    fn parse_operation_timeout() {
+        let operation: TestOperation = serde_json::from_str(r#"{
+            "type": "Eval",
+            "code": "new Promise(() => {})",
+            "timeout": 500
+        }"#).expect("Unable to parse operation with timeout");
+        let instruction = operation.clone().into_instruction(TabDescriptor::Title { title: "SP".into() }, Some(1_000));
+        assert!(matches!(instruction, Instruction::Operation(TestOp { timeout: Some(t), .. }) if t.as_millis() == 500));
+        let operation = TestOperation { timeout: None, ..operation };
+        let instruction = operation.into_instruction(TabDescriptor::Title { title: "SP".into() }, Some(1_000));
+        assert!(matches!(instruction, Instruction::Operation(TestOp { timeout: Some(t), .. }) if t.as_millis() == 1_000));
+    }
+
+    #[test]
+    fn parse_eventually_assertion() {
+        let assertion: TestInstruction = serde_json::from_str(r#"{
+            "type": "Assert",
+            "element": { "by": "CSS", "css": "div.status" },
+            "assert": { "TextEquals": "Done" },
+            "eventually": { "timeout": 2000 }
+        }"#).expect("Unable to parse eventually assertion");
+        let instruction = assertion.into_instruction(TabDescriptor::Title { title: "SP".into() }, None);
+        if let Instruction::Assertion(TestAssert { eventually: Some(polling), .. }) = instruction {
+            assert_eq!(polling.timeout, Some(std::time::Duration::from_millis(2000)));
+            assert_eq!(polling.interval, std::time::Duration::from_millis(100));
+            return;
+        }
+        panic!("Eventually assertion parsed into wrong instruction");
+    }
+
+    #[test]
+    fn parse_chained_count_assertion() {
+        let assertion: TestInstruction = serde_json::from_str(r#"{
+            "type": "Assert",
+            "element": {
+                "by": "Within",
+                "parent": { "by": "Nth", "selector": { "by": "CSS", "css": "ul" }, "index": 1 },
+                "child": { "by": "CSS", "css": "li" }
+            },
+            "assert": { "Count": { "Equals": 3 } }
+        }"#).expect("Unable to parse count assertion");
+        let instruction = assertion.into_instruction(TabDescriptor::Title { title: "SP".into() }, None);
+        if let Instruction::Assertion(TestAssert { assertion: GeneralAssertType::Element(elem), .. }) = instruction {
+            assert_eq!(elem.element.to_string(), "Element[css~`ul`][1] > Element[css~`li`]");
+            if let ElementAssertionType::Count(comparison) = elem.assert {
+                assert!(comparison.compare(Some(&3.into())));
+                return;
+            }
+        }
+        panic!("Count assertion parsed into wrong instruction");
+    }
+
+    #[test]
+    fn parse_eval_assertion() {
+        let eval: TestInstruction = serde_json::from_str(r#"{
+            "type": "Eval",
+            "code": "1 + 1",
+            "assert": { "Equals": 2 }
+        }"#).expect("Unable to parse eval assertion");
+        let instruction = eval.into_instruction(TabDescriptor::Title { title: "SP".into() }, None);
+        if let Instruction::Assertion(TestAssert { assertion: GeneralAssertType::Tab(TabAssert::Evaluate { script, comparison }), .. }) = instruction {
+            assert_eq!(script, "1 + 1");
+            assert!(comparison.compare(Some(&2.into())));
+        } else {
+            panic!("Eval assertion parsed into wrong instruction");
+        }
+
+        let eval: TestInstruction = serde_json::from_str(r#"{ "type": "Eval", "code": "1 + 1" }"#)
+            .expect("Unable to parse eval without assertion");
+        let instruction = eval.into_instruction(TabDescriptor::Title { title: "SP".into() }, None);
+        assert!(matches!(instruction, Instruction::Operation(TestOp { op: GeneralOpType::Tab(TabOpType::Evaluate(_)), .. })));
+    }
+}
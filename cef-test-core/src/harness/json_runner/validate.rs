@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+
+use super::{JsonRunner, Test, TestStepType, MacroStep, IfStep, LoopStep, ForEachStep};
+
+/// Problem found in a test file
+#[derive(Debug, Clone)]
+pub struct ValidationError {
+    /// File with the problem
+    pub file: PathBuf,
+    /// Problem description, including where it is in the file
+    pub message: String,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.file.display(), self.message)
+    }
+}
+
+impl JsonRunner {
+    /// JSON Schema of test files, for editor completion and validation
+    pub fn schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(Test)).expect("JSON Schema is always valid JSON")
+    }
+
+    /// Check a test file, the macros it includes and the files it refers to without running anything
+    pub fn validate<P: AsRef<Path>>(path: P, macro_path: &[PathBuf]) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        validate_file(path.as_ref(), macro_path, &mut Vec::new(), &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn validate_file(path: &Path, macro_path: &[PathBuf], includes: &mut Vec<PathBuf>, errors: &mut Vec<ValidationError>) {
+    let error = |message: String| ValidationError { file: path.to_owned(), message };
+    let runner = match JsonRunner::from_file(path) {
+        Ok(runner) => runner.with_macro_path(macro_path.to_vec()),
+        Err(e) => {
+            errors.push(error(e.to_string()));
+            return;
+        }
+    };
+    if let Some(parameters) = &runner.test_data.parameters {
+        if let Err(e) = parameters.rows(runner.filepath.as_deref().and_then(Path::parent)) {
+            errors.push(error(format!("parameters: {}", e)));
+        }
+    }
+    includes.push(path.canonicalize().unwrap_or_else(|_| path.to_owned()));
+    let test = &runner.test_data;
+    for (phase, steps) in [("setup", &test.setup), ("test", &test.test), ("teardown", &test.teardown)] {
+        validate_steps(&runner, steps, phase, macro_path, includes, errors);
+    }
+    includes.pop();
+}
+
+fn validate_steps(runner: &JsonRunner, steps: &[TestStepType], path: &str, macro_path: &[PathBuf], includes: &mut Vec<PathBuf>, errors: &mut Vec<ValidationError>) {
+    for (i, step) in steps.iter().enumerate() {
+        let path = format!("{}[{}]", path, i);
+        match step {
+            TestStepType::Regular(_) => {},
+            TestStepType::Macro(MacroStep { name, file, .. }) => {
+                let macro_file = runner.resolve_macro(file);
+                match macro_file.canonicalize() {
+                    Ok(canonical) if includes.contains(&canonical) => errors.push(ValidationError {
+                        file: runner.filepath.clone().unwrap_or_default(),
+                        message: format!("{}: macro {} ({}) is included recursively", path, name, file.display()),
+                    }),
+                    Ok(_) => validate_file(&macro_file, macro_path, includes, errors),
+                    Err(e) => errors.push(ValidationError {
+                        file: runner.filepath.clone().unwrap_or_default(),
+                        message: format!("{}: macro {} ({}) not found: {}", path, name, file.display(), e),
+                    }),
+                }
+            },
+            TestStepType::If(IfStep { then, otherwise, .. }) => {
+                validate_steps(runner, then, &format!("{}.then", path), macro_path, includes, errors);
+                validate_steps(runner, otherwise, &format!("{}.else", path), macro_path, includes, errors);
+            },
+            TestStepType::Loop(LoopStep { repeat, .. }) => validate_steps(runner, repeat, &format!("{}.repeat", path), macro_path, includes, errors),
+            TestStepType::ForEach(ForEachStep { steps, .. }) => validate_steps(runner, steps, &format!("{}.steps", path), macro_path, includes, errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validate_reports_location() {
+        let dir = std::env::temp_dir().join(format!("cef-test-validate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("Unable to create test directory");
+        let info = r#""info": { "name": "Validate test", "blame": "cef-test", "id": "validate", "output": "validate.json", "fail_mode": "FastFail" }"#;
+        std::fs::write(dir.join("bad.json"), format!(r#"{{ {}, "test": [
+            {{ "tab": {{ "by": "Title", "title": "SP" }}, "operations": [ {{ "type": "Sleep", "milliseconds": 1 }} ] }},
+            {{ "if": {{ "tab": {{ "by": "Title", "title": "SP" }}, "type": "Eval", "code": "x" }}, "then": [
+                {{ "tab": {{ "by": "Title", "title": "SP" }}, "operations": [ {{ "type": "Element", "element": {{ "by": "Nope" }}, "operation": "Click" }} ] }}
+            ] }}
+        ] }}"#, info)).expect("Unable to write test");
+        std::fs::write(dir.join("main.json"), format!(r#"{{ {}, "test": [
+            {{ "name": "Bad", "file": "bad.json" }},
+            {{ "name": "Missing", "file": "missing.json" }}
+        ] }}"#, info)).expect("Unable to write test");
+        let errors = JsonRunner::validate(dir.join("main.json"), &[]).expect_err("Expected validation errors");
+        std::fs::remove_dir_all(&dir).expect("Unable to remove test directory");
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].message.starts_with("test[1].then[0].operations[0].element.by: unknown variant `Nope`"), "{}", errors[0]);
+        assert!(errors[1].message.starts_with("test[1]: macro Missing"), "{}", errors[1]);
+        assert!(JsonRunner::schema()["definitions"].get("TestStepType").is_some());
+    }
+}
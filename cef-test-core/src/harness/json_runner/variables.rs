@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// Named values which can be used in tests with `${name}`.
+/// Unknown names are left alone, so Javascript template literals keep working; `$${` escapes `${`.
+#[derive(Default, Debug, Clone)]
+pub struct Variables {
+    values: HashMap<String, Value>,
+}
+
+impl Variables {
+    /// Get variable value
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    /// Set variable value
+    pub fn set(&mut self, name: String, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    /// Remove variable
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        self.values.remove(name)
+    }
+
+    /// Replace `${name}` in every string of a test item.
+    /// A string which is exactly `${name}` becomes the variable's value (e.g. a number) when that's valid for the item.
+    pub fn interpolate<T: Serialize + DeserializeOwned + Clone>(&self, item: &T) -> T {
+        if self.values.is_empty() {
+            return item.clone();
+        }
+        let json = match serde_json::to_value(item) {
+            Ok(json) => json,
+            Err(e) => {
+                log::warn!("Unable to interpolate variables: {}", e);
+                return item.clone();
+            }
+        };
+        serde_json::from_value(self.interpolate_json(&json, true))
+            .or_else(|_| serde_json::from_value(self.interpolate_json(&json, false)))
+            .unwrap_or_else(|e| {
+                log::warn!("Unable to interpolate variables: {}", e);
+                item.clone()
+            })
+    }
+
+    fn interpolate_json(&self, json: &Value, typed: bool) -> Value {
+        match json {
+            Value::String(s) => {
+                if typed {
+                    if let Some(value) = s.strip_prefix("${").and_then(|s| s.strip_suffix('}')).and_then(|name| self.values.get(name)) {
+                        return value.clone();
+                    }
+                }
+                Value::String(self.interpolate_str(s))
+            },
+            Value::Array(items) => Value::Array(items.iter().map(|item| self.interpolate_json(item, typed)).collect()),
+            Value::Object(fields) => Value::Object(fields.iter().map(|(k, v)| (k.clone(), self.interpolate_json(v, typed))).collect()),
+            other => other.clone(),
+        }
+    }
+
+    /// Replace `${name}` in text
+    pub fn interpolate_str(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('$') {
+            result.push_str(&rest[..start]);
+            let tail = &rest[start..];
+            if let Some(escaped) = tail.strip_prefix("$${") {
+                result.push_str("${");
+                rest = escaped;
+            } else if let Some((name, after)) = tail.strip_prefix("${").and_then(|t| t.split_once('}')) {
+                match self.values.get(name) {
+                    Some(Value::String(s)) => result.push_str(s),
+                    Some(value) => result.push_str(&value.to_string()),
+                    None => result.push_str(&tail[..tail.len() - after.len()]),
+                }
+                rest = after;
+            } else {
+                result.push('$');
+                rest = &tail[1..];
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interpolate_text() {
+        let mut vars = Variables::default();
+        vars.set("user".into(), "deck".into());
+        vars.set("count".into(), 3.into());
+        assert_eq!(vars.interpolate_str("hi ${user} x${count}"), "hi deck x3");
+        assert_eq!(vars.interpolate_str("`${jsVar}` $${user} $5"), "`${jsVar}` ${user} $5");
+    }
+
+    #[test]
+    fn interpolate_typed() {
+        let mut vars = Variables::default();
+        vars.set("count".into(), 3.into());
+        let typed: Value = vars.interpolate(&serde_json::json!({ "Equals": "${count}", "TextEquals": "n=${count}" }));
+        assert_eq!(typed, serde_json::json!({ "Equals": 3, "TextEquals": "n=3" }));
+        let text: String = vars.interpolate(&"${count}".to_owned());
+        assert_eq!(text, "3");
+    }
+}
@@ -8,11 +8,12 @@ mod headless_adapter;
 mod instructions;
 mod json_runner;
 mod runner;
+mod selector_js;
 
 pub use adapter::TestAdapter;
-pub use feedback::Feedback;
+pub use feedback::{Feedback, TestError};
 pub use harness::TestHarness;
 pub use headless_adapter::HeadlessAdapter;
-pub use instructions::{Instruction, TestAssert, GeneralAssertType, ElementAssert, ElementAssertionType, TestOp, ElementSelector, TabSelector, GeneralOpType, TabOpType, ElementOp, ElementOpType, TabAssert, Comparison};
-pub use json_runner::JsonRunner;
+pub use instructions::{Instruction, TestAssert, Polling, GeneralAssertType, ElementAssert, ElementAssertionType, TestOp, ElementSelector, TabSelector, GeneralOpType, TabOpType, ElementOp, ElementOpType, TabAssert, Comparison, KeyModifier, GamepadButton};
+pub use json_runner::{JsonRunner, ValidationError};
 pub use runner::{TestRunner, TestMetadata};
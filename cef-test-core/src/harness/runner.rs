@@ -6,6 +6,18 @@ pub trait TestRunner: Send + Sync {
 
     /// Get test information
     fn meta(&self) -> TestMetadata;
+
+    /// Prepare to run the test again after it failed.
+    /// Returns false if the test should not be run again.
+    fn retry(&mut self) -> bool {
+        false
+    }
+
+    /// Whether failing feedback can be recovered from (e.g. by retrying), so that only meta() decides success.
+    /// Otherwise any failing feedback fails the test.
+    fn recovers_failures(&self) -> bool {
+        false
+    }
 }
 
 /// Information about the test and the run
@@ -25,6 +37,12 @@ pub struct TestMetadata {
 
     /// Was the test successful, or (if incomplete) is it currently passing?
     pub success: bool,
+
+    /// Cause of the test failure
+    pub error: Option<super::TestError>,
+
+    /// Number of times an instruction, step or the whole test was retried
+    pub retries: u32,
 }
 
 impl std::fmt::Display for TestMetadata {
@@ -38,6 +56,12 @@ impl std::fmt::Display for TestMetadata {
         } else {
             write!(f, " FAILURE")?;
         }
+        if self.retries != 0 {
+            write!(f, " after {} retries", self.retries)?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " [{}]", error)?;
+        }
         if let Some(output) = &self.output {
             write!(f, " ({})", output.display())?;
         }
@@ -0,0 +1,262 @@
+use super::{ElementSelector, TabSelector};
+
+/// Helper functions available to every element query
+const PRELUDE: &str = r#"
+    const cefUnique = (elements) => [...new Set(elements)];
+    const cefFrameDocument = (frame) => {
+        // cross-origin frames throw (or return null) instead of exposing their document
+        try {
+            return frame.contentDocument || null;
+        } catch (e) {
+            return null;
+        }
+    };
+    const cefDeepAll = (root, css) => {
+        const found = Array.from(root.querySelectorAll(css));
+        for (const e of root.querySelectorAll("*")) {
+            if (e.shadowRoot) found.push(...cefDeepAll(e.shadowRoot, css));
+            if (e.tagName === "IFRAME" || e.tagName === "FRAME") {
+                const inner = cefFrameDocument(e);
+                if (inner) found.push(...cefDeepAll(inner, css));
+            }
+        }
+        return found;
+    };
+    const cefVisible = (e) => {
+        const style = getComputedStyle(e);
+        const rect = e.getBoundingClientRect();
+        return style.display !== "none" && style.visibility !== "hidden" && parseFloat(style.opacity) > 0
+            && rect.width > 0 && rect.height > 0;
+    };
+    const cefText = (e) => (e.innerText ?? e.textContent ?? "").trim();
+    const cefDeepest = (matches) => matches.filter((e) => !matches.some((o) => o !== e && e.contains(o)));
+    const cefRole = (e) => {
+        const explicit = e.getAttribute("role");
+        if (explicit) return explicit.split(" ")[0];
+        const tag = e.tagName.toLowerCase();
+        const type = (e.getAttribute("type") || "text").toLowerCase();
+        switch (tag) {
+            case "a": return e.hasAttribute("href") ? "link" : null;
+            case "button": return "button";
+            case "dialog": return "dialog";
+            case "h1": case "h2": case "h3": case "h4": case "h5": case "h6": return "heading";
+            case "img": return e.getAttribute("alt") === "" ? "presentation" : "img";
+            case "li": return "listitem";
+            case "nav": return "navigation";
+            case "ol": case "ul": return "list";
+            case "option": return "option";
+            case "progress": return "progressbar";
+            case "select": return e.multiple || e.size > 1 ? "listbox" : "combobox";
+            case "table": return "table";
+            case "textarea": return "textbox";
+            case "input":
+                switch (type) {
+                    case "button": case "image": case "reset": case "submit": return "button";
+                    case "checkbox": return "checkbox";
+                    case "radio": return "radio";
+                    case "range": return "slider";
+                    case "number": return "spinbutton";
+                    case "search": return "searchbox";
+                    default: return "textbox";
+                }
+            default: return null;
+        }
+    };
+    const cefName = (e) => {
+        const label = e.getAttribute("aria-label");
+        if (label) return label.trim();
+        const labelledBy = e.getAttribute("aria-labelledby");
+        if (labelledBy) {
+            return labelledBy.split(" ")
+                .map((id) => document.getElementById(id))
+                .filter((l) => l)
+                .map(cefText)
+                .join(" ");
+        }
+        if (e.labels && e.labels.length) return Array.from(e.labels).map(cefText).join(" ");
+        return (e.getAttribute("alt") || cefText(e) || e.getAttribute("title") || e.getAttribute("placeholder") || "").trim();
+    };
+    const cefFiber = (e) => {
+        const key = Object.keys(e).find((k) => k.startsWith("__reactFiber$") || k.startsWith("__reactInternalInstance$"));
+        return key ? e[key] : null;
+    };
+    const cefComponentName = (f) => {
+        const t = f.type;
+        if (!t || typeof t === "string") return null;
+        const inner = t.render || t.type;
+        return t.displayName || t.name || (inner && (inner.displayName || inner.name)) || null;
+    };
+    const cefPropsMatch = (actual, wanted) => {
+        if (wanted !== null && typeof wanted === "object") {
+            return actual !== null && typeof actual === "object"
+                && Object.keys(wanted).every((k) => cefPropsMatch(actual[k], wanted[k]));
+        }
+        return actual === wanted;
+    };
+    const cefHostNodes = (f) => {
+        const nodes = [];
+        const walk = (c) => {
+            for (; c; c = c.sibling) {
+                if (c.stateNode instanceof Element) nodes.push(c.stateNode);
+                else walk(c.child);
+            }
+        };
+        walk(f.child);
+        return nodes;
+    };
+"#;
+
+fn js_str(s: &str) -> String {
+    serde_json::Value::from(s).to_string()
+}
+
+/// Javascript function expression `(root) => Element[]` which finds all matches of the selector below root
+pub(super) fn query_all(selector: &ElementSelector) -> String {
+    match selector {
+        ElementSelector::CSS(css) => format!("((root) => Array.from(root.querySelectorAll({})))", js_str(css)),
+        ElementSelector::XPath(xpath) => format!("((root) => {{
+            const result = (root.ownerDocument || root).evaluate({}, root, null, XPathResult.ORDERED_NODE_SNAPSHOT_TYPE, null);
+            const elements = [];
+            for (let i = 0; i < result.snapshotLength; i++) elements.push(result.snapshotItem(i));
+            return elements;
+        }})", js_str(xpath)),
+        ElementSelector::Text(text) => format!(
+            "((root) => cefDeepest(Array.from(root.querySelectorAll(\"*\")).filter((e) => cefText(e) === {})))",
+            js_str(text)),
+        ElementSelector::TextContains(text) => format!(
+            "((root) => cefDeepest(Array.from(root.querySelectorAll(\"*\")).filter((e) => cefText(e).includes({}))))",
+            js_str(text)),
+        ElementSelector::Role { role, name } => format!(
+            "((root) => Array.from(root.querySelectorAll(\"*\")).filter((e) => cefRole(e) === {} && ({} === null || cefName(e) === {})))",
+            js_str(role),
+            name.as_deref().map(js_str).unwrap_or_else(|| "null".to_owned()),
+            name.as_deref().map(js_str).unwrap_or_else(|| "null".to_owned())),
+        ElementSelector::Attribute { attribute, value } => format!(
+            "((root) => Array.from(root.querySelectorAll(\"[\" + CSS.escape({}) + \"]\")).filter((e) => e.getAttribute({}) === {}))",
+            js_str(attribute), js_str(attribute), js_str(value)),
+        ElementSelector::Placeholder(placeholder) => format!(
+            "((root) => Array.from(root.querySelectorAll(\"[placeholder]\")).filter((e) => e.getAttribute(\"placeholder\") === {}))",
+            js_str(placeholder)),
+        ElementSelector::Label(label) => format!("((root) => {{
+            const labelled = Array.from(root.querySelectorAll(\"label\"))
+                .filter((l) => cefText(l) === {0})
+                .map((l) => l.control)
+                .filter((c) => c);
+            const aria = Array.from(root.querySelectorAll(\"[aria-label]\"))
+                .filter((e) => e.getAttribute(\"aria-label\") === {0});
+            return [...new Set([...labelled, ...aria])];
+        }})", js_str(label)),
+        ElementSelector::React { component, props } => format!("((root) => {{
+            const component = {};
+            const props = {};
+            let top = null;
+            for (const e of [root, ...root.querySelectorAll(\"*\")]) {{
+                top = cefFiber(e);
+                if (top) break;
+            }}
+            if (!top) return [];
+            while (top.return) top = top.return;
+            // the root fiber we climbed to may be the stale alternate tree
+            if (top.stateNode && top.stateNode.current) top = top.stateNode.current;
+            const found = [];
+            const stack = [top];
+            while (stack.length) {{
+                const f = stack.pop();
+                if (cefComponentName(f) === component && cefPropsMatch(f.memoizedProps, props)) {{
+                    const node = cefHostNodes(f).find((n) => root.contains(n));
+                    if (node && !found.includes(node)) found.push(node);
+                }}
+                if (f.sibling) stack.push(f.sibling);
+                if (f.child) stack.push(f.child);
+            }}
+            return found;
+        }})", js_str(component), serde_json::Value::Object(props.clone())),
+        ElementSelector::Nth { selector, index } => format!(
+            "((root) => {}(root).slice({1}, {1} + 1))",
+            query_all(selector), index),
+        ElementSelector::Last(selector) => format!("((root) => {}(root).slice(-1))", query_all(selector)),
+        ElementSelector::Within { parent, child } => format!(
+            "((root) => {{ const child = {}; return cefUnique({}(root).flatMap((p) => child(p))); }})",
+            query_all(child), query_all(parent)),
+        ElementSelector::Parent(selector) => format!(
+            "((root) => cefUnique({}(root).map((e) => e.parentElement).filter((e) => e)))",
+            query_all(selector)),
+        ElementSelector::Sibling { of, sibling } => format!("((root) => {{
+            const sibling = {};
+            return cefUnique({}(root)
+                .filter((e) => e.parentElement)
+                .flatMap((e) => sibling(e.parentElement).filter((s) => s !== e && s.parentElement === e.parentElement)));
+        }})", query_all(sibling), query_all(of)),
+        ElementSelector::WithText { selector, text } => format!(
+            "((root) => {}(root).filter((e) => cefText(e).includes({})))",
+            query_all(selector), js_str(text)),
+        ElementSelector::Frame { frame, child } => format!("((root) => {{
+            const child = {};
+            return cefUnique({}(root).map(cefFrameDocument).filter((d) => d).flatMap((d) => child(d)));
+        }})", query_all(child), query_all(frame)),
+        ElementSelector::Shadow { host, child } => format!("((root) => {{
+            const child = {};
+            return cefUnique({}(root).filter((h) => h.shadowRoot).flatMap((h) => child(h.shadowRoot)));
+        }})", query_all(child), query_all(host)),
+        ElementSelector::DeepCSS(css) => format!("((root) => cefUnique(cefDeepAll(root, {})))", js_str(css)),
+    }
+}
+
+/// Javascript expression which evaluates to the document selected by the tab selector, or null
+fn root_document(tab: &TabSelector) -> String {
+    match tab {
+        TabSelector::Frame { tab, frame } => format!(
+            "((root) => {{ const frame = root && {}(root)[0]; return frame ? cefFrameDocument(frame) : null; }})({})",
+            query_all(frame), root_document(tab)),
+        _ => "document".to_owned(),
+    }
+}
+
+/// Javascript expression which evaluates to the first element matching the selector, or null
+pub(super) fn find_first(tab: &TabSelector, selector: &ElementSelector) -> String {
+    format!("(() => {{ {} const root = {}; return root ? {}(root)[0] || null : null; }})()",
+        PRELUDE, root_document(tab), query_all(selector))
+}
+
+/// Javascript expression which evaluates to the number of elements matching the selector
+pub(super) fn count(tab: &TabSelector, selector: &ElementSelector) -> String {
+    format!("(() => {{ {} const root = {}; return root ? {}(root).length : 0; }})()",
+        PRELUDE, root_document(tab), query_all(selector))
+}
+
+/// Javascript expression which evaluates script in the global context of the frame selected by the tab selector
+pub(super) fn in_frame(tab: &TabSelector, script: &str) -> String {
+    match tab {
+        TabSelector::Frame { .. } => format!(
+            "(() => {{ {} const root = {}; if (!root) throw new Error({}); return root.defaultView.eval({}); }})()",
+            PRELUDE, root_document(tab), js_str(&format!("Failed to find frame {}", tab)), js_str(script)),
+        _ => script.to_owned(),
+    }
+}
+
+/// Javascript expression which evaluates check, a function `(Element | null) => bool`, on the first element matching the selector
+pub(super) fn check_first(tab: &TabSelector, selector: &ElementSelector, check: &str) -> String {
+    format!("(() => {{ {} const root = {}; return ({})(root ? {}(root)[0] || null : null); }})()",
+        PRELUDE, root_document(tab), check, query_all(selector))
+}
+
+/// Check function for `check_first` which tests element visibility
+pub(super) fn visible_check(visible: bool) -> &'static str {
+    if visible {
+        "(e) => !!e && cefVisible(e)"
+    } else {
+        "(e) => !e || !cefVisible(e)"
+    }
+}
+
+/// Check function for `check_first` which tests element is enabled
+pub(super) const ENABLED_CHECK: &str = "(e) => !!e && !e.matches(\":disabled\") && e.getAttribute(\"aria-disabled\") !== \"true\"";
+
+/// Check function for `check_first` which tests element text
+pub(super) fn text_check(text: &str, exact: bool) -> String {
+    if exact {
+        format!("(e) => !!e && cefText(e) === {}", js_str(text))
+    } else {
+        format!("(e) => !!e && cefText(e).includes({})", js_str(text))
+    }
+}
@@ -4,3 +4,14 @@
 pub fn timestamp_now() -> String {
     chrono::offset::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false)
 }
+
+/// Javascript truthiness of a value
+pub fn is_truthy(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::Number(n) => n.as_f64().map(|n| n != 0.0 && !n.is_nan()).unwrap_or(true),
+        serde_json::Value::String(s) => !s.is_empty(),
+        _ => true,
+    }
+}
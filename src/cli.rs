@@ -1,5 +1,5 @@
 use std::path::PathBuf;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 /// -WIP- Automated test tool for CEF UIs
 #[derive(Parser, Debug)]
@@ -13,8 +13,48 @@ pub struct Cli {
     #[arg(short, long)]
     pub address: Option<String>,
 
+    /// Default instruction timeout, in milliseconds
+    #[arg(short, long)]
+    pub timeout: Option<u64>,
+
+    /// Test file to run once before all tests
+    #[arg(long)]
+    pub before_all: Option<PathBuf>,
+
+    /// Test file to run once after all tests
+    #[arg(long)]
+    pub after_all: Option<PathBuf>,
+
+    /// Directory to search for macros which aren't next to the including test file
+    #[arg(long)]
+    pub macro_path: Vec<PathBuf>,
+
+    /// Test variable, as NAME=VALUE (also read from CEF_TEST_VAR_<NAME> environment variables)
+    #[arg(long = "var", value_parser = parse_variable)]
+    pub variables: Vec<(String, String)>,
+
     /// Test file(s)
     pub test: Vec<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check test files, their macros and referenced files without running them
+    Validate {
+        /// Test file(s)
+        files: Vec<PathBuf>,
+    },
+    /// Print the JSON Schema of test files
+    Schema,
+}
+
+fn parse_variable(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got `{}`", arg))
 }
 
 impl Cli {
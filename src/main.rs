@@ -1,12 +1,23 @@
 mod cli;
 
+use std::collections::HashMap;
+
 use simplelog::{LevelFilter, WriteLogger, TermLogger, CombinedLogger};
 
-const PACKAGE_NAME: &'static str = env!("CARGO_PKG_NAME");
-const PACKAGE_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+const PACKAGE_NAME: &str = env!("CARGO_PKG_NAME");
+const PACKAGE_VERSION: &str = env!("CARGO_PKG_VERSION");
+const VARIABLE_ENV_PREFIX: &str = "CEF_TEST_VAR_";
 
 fn main() -> Result<(), String> {
     let args = cli::Cli::parse();
+    match &args.command {
+        Some(cli::Command::Validate { files }) => return validate(files, &args.macro_path),
+        Some(cli::Command::Schema) => {
+            println!("{:#}", cef_test_core::harness::JsonRunner::schema());
+            return Ok(());
+        },
+        None => {},
+    }
     println!("Got args {:?}", &args);
 
     let log_filepath = format!("./{}-{}-v{}.log", cef_test_core::util::timestamp_now(), PACKAGE_NAME, PACKAGE_VERSION);
@@ -58,14 +69,32 @@ fn main() -> Result<(), String> {
     log::info!("Initializing test adapter");
     let adapter = cef_test_core::harness::HeadlessAdapter::connect(&addr, port).map_err(|e| e.to_string())?;
 
+    // command line variables take priority over the environment
+    let mut variables: HashMap<String, String> = std::env::vars()
+        .filter_map(|(key, value)| key.strip_prefix(VARIABLE_ENV_PREFIX).map(|name| (name.to_owned(), value)))
+        .collect();
+    variables.extend(args.variables);
+    let load_runner = |path: std::path::PathBuf| cef_test_core::harness::JsonRunner::from_file(path)
+        .map(|runner| runner.with_variables(variables.clone()).with_macro_path(args.macro_path.clone()))
+        .map_err(|e| e.to_string());
+
     log::info!("Initializing test runners");
     let mut runners = Vec::with_capacity(args.test.len());
 
     for test_file in args.test {
-        runners.push(cef_test_core::harness::JsonRunner::from_file(test_file).map_err(|e| e.to_string())?);
+        runners.extend(load_runner(test_file)?.instances().map_err(|e| e.to_string())?);
     }
     log::info!("Initializing test harness");
-    let harness = cef_test_core::harness::TestHarness::new(adapter, runners);
+    let mut harness = cef_test_core::harness::TestHarness::new(adapter, runners);
+    if let Some(timeout) = args.timeout {
+        harness = harness.with_timeout(std::time::Duration::from_millis(timeout));
+    }
+    if let Some(before_all) = args.before_all {
+        harness = harness.with_before_all(load_runner(before_all)?);
+    }
+    if let Some(after_all) = args.after_all {
+        harness = harness.with_after_all(load_runner(after_all)?);
+    }
 
     log::info!("Starting test harness");
     if let Err(errs) = harness.execute() {
@@ -74,3 +103,23 @@ fn main() -> Result<(), String> {
         Ok(())
     }
 }
+
+fn validate(files: &[std::path::PathBuf], macro_path: &[std::path::PathBuf]) -> Result<(), String> {
+    let mut problems = 0;
+    for file in files {
+        match cef_test_core::harness::JsonRunner::validate(file, macro_path) {
+            Ok(()) => println!("{}: ok", file.display()),
+            Err(errors) => {
+                problems += errors.len();
+                for error in errors {
+                    println!("{}", error);
+                }
+            }
+        }
+    }
+    if problems == 0 {
+        Ok(())
+    } else {
+        Err(format!("{} problems found.", problems))
+    }
+}